@@ -0,0 +1,18 @@
+//! Constants used throughout the kernel.
+
+pub const PAGE_SIZE: usize = 0x1000;
+pub const PAGE_SIZE_BITS: usize = 0xc;
+pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
+
+/// Top of the user address space reserved for the trampoline / trap context.
+pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
+pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE;
+
+/// Default amount of physical memory available to the frame allocator,
+/// overridable at runtime via [`crate::mm::set_memory_end`].
+pub const MEMORY_END: usize = 0x8800_0000;
+
+pub const USER_STACK_SIZE: usize = PAGE_SIZE * 16;
+pub const KERNEL_STACK_SIZE: usize = PAGE_SIZE * 2;
+
+pub const CLOCK_FREQ: usize = 12_500_000;
@@ -0,0 +1,142 @@
+//! A minimal flattened-device-tree (DTB) reader, just enough to find the
+//! platform's memory size and MMIO device regions at boot instead of
+//! hard-coding them in [`crate::config`]. Not a general FDT library: it
+//! only understands the handful of token types and properties this kernel
+//! actually needs.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+#[repr(C)]
+struct FdtHeader {
+    magic: u32,
+    totalsize: u32,
+    off_dt_struct: u32,
+    off_dt_strings: u32,
+    off_mem_rsvmap: u32,
+    version: u32,
+    last_comp_version: u32,
+    boot_cpuid_phys: u32,
+    size_dt_strings: u32,
+    size_dt_struct: u32,
+}
+
+/// One `reg = <base size>` pair discovered in the device tree, identified
+/// by the owning node's name (e.g. `"virtio_mmio@10001000"`).
+pub struct DeviceRegion {
+    pub name: String,
+    pub base: usize,
+    pub size: usize,
+}
+
+fn read_be32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn read_be64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_cstr(bytes: &[u8], offset: usize) -> &str {
+    let end = bytes[offset..]
+        .iter()
+        .position(|&b| b == 0)
+        .map(|n| offset + n)
+        .unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[offset..end]).unwrap_or("")
+}
+
+/// Walk the struct block of the DTB at physical address `dtb_pa`, returning
+/// every node whose `reg` property looks like a single `<base size>` pair —
+/// covers both the `memory@...` node and the platform's `*_mmio@...` device
+/// nodes, which is all this kernel currently needs from the tree.
+///
+/// # Safety
+/// `dtb_pa` must point to a valid, `FdtHeader::totalsize`-byte flattened
+/// device tree, as handed to the kernel by the bootloader in `a1`.
+pub unsafe fn parse_regions(dtb_pa: usize) -> Vec<DeviceRegion> {
+    let header = &*(dtb_pa as *const FdtHeader);
+    if u32::from_be(header.magic) != FDT_MAGIC {
+        return Vec::new();
+    }
+    let total_size = u32::from_be(header.totalsize) as usize;
+    let bytes = core::slice::from_raw_parts(dtb_pa as *const u8, total_size);
+    let struct_off = u32::from_be(header.off_dt_struct) as usize;
+    let struct_size = u32::from_be(header.size_dt_struct) as usize;
+    let strings_off = u32::from_be(header.off_dt_strings) as usize;
+
+    let mut regions = Vec::new();
+    let mut pos = struct_off;
+    let struct_end = struct_off + struct_size;
+    let mut node_stack: Vec<String> = Vec::new();
+
+    while pos + 4 <= struct_end {
+        let token = read_be32(bytes, pos);
+        pos += 4;
+        match token {
+            t if t == FDT_BEGIN_NODE => {
+                let name = read_cstr(bytes, pos);
+                node_stack.push(String::from(name));
+                pos += name.len() + 1;
+                pos = (pos + 3) & !3;
+            }
+            t if t == FDT_END_NODE => {
+                node_stack.pop();
+            }
+            t if t == FDT_PROP => {
+                let len = read_be32(bytes, pos) as usize;
+                let nameoff = read_be32(bytes, pos + 4) as usize;
+                let data_off = pos + 8;
+                let prop_name = read_cstr(bytes, strings_off + nameoff);
+                if prop_name == "reg" && len >= 16 {
+                    let base = read_be64(bytes, data_off) as usize;
+                    let size = read_be64(bytes, data_off + 8) as usize;
+                    if let Some(name) = node_stack.last() {
+                        regions.push(DeviceRegion {
+                            name: name.clone(),
+                            base,
+                            size,
+                        });
+                    }
+                }
+                pos = data_off + len;
+                pos = (pos + 3) & !3;
+            }
+            t if t == FDT_NOP => {}
+            t if t == FDT_END => break,
+            _ => break,
+        }
+    }
+    regions
+}
+
+/// The end of physical memory, from the `memory@...` node's `reg` property,
+/// or `None` if the tree has no such node (or isn't a valid DTB at all).
+pub fn memory_end_from_dtb(dtb_pa: usize) -> Option<usize> {
+    let regions = unsafe { parse_regions(dtb_pa) };
+    regions
+        .iter()
+        .find(|r| r.name.starts_with("memory@"))
+        .map(|r| r.base + r.size)
+}
+
+/// Every `*_mmio@...` device region in the tree, for mapping into a
+/// process's address space as [`crate::mm::MapType::Device`] areas.
+pub fn mmio_regions_from_dtb(dtb_pa: usize) -> Vec<DeviceRegion> {
+    unsafe { parse_regions(dtb_pa) }
+        .into_iter()
+        .filter(|r| r.name.contains("mmio@"))
+        .collect()
+}
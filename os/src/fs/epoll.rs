@@ -0,0 +1,78 @@
+//! Minimal level-triggered epoll. The instance itself is a [`File`] holding
+//! the set of watched fds and their requested events; there's no
+//! event-driven wakeup machinery in this tree, so `epoll_wait` just re-polls
+//! each watched fd's own `poll_read`/`poll_write` until something is ready
+//! or the timeout elapses.
+
+use super::{File, FileError};
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+pub const EPOLLIN: u32 = 0x001;
+pub const EPOLLOUT: u32 = 0x004;
+
+pub const EPOLL_CTL_ADD: i32 = 1;
+pub const EPOLL_CTL_DEL: i32 = 2;
+pub const EPOLL_CTL_MOD: i32 = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct EpollEvent {
+    pub events: u32,
+    pub data: u64,
+}
+
+pub struct Epoll {
+    watched: UPSafeCell<BTreeMap<usize, EpollEvent>>,
+}
+
+impl Epoll {
+    pub fn new() -> Self {
+        Self {
+            watched: unsafe { UPSafeCell::new(BTreeMap::new()) },
+        }
+    }
+
+    pub fn ctl(&self, op: i32, fd: usize, event: EpollEvent) -> isize {
+        let mut watched = self.watched.exclusive_access();
+        match op {
+            EPOLL_CTL_ADD | EPOLL_CTL_MOD => {
+                watched.insert(fd, event);
+                0
+            }
+            EPOLL_CTL_DEL => {
+                watched.remove(&fd);
+                0
+            }
+            _ => -1,
+        }
+    }
+
+    pub fn watched(&self) -> Vec<(usize, EpollEvent)> {
+        self.watched
+            .exclusive_access()
+            .iter()
+            .map(|(&fd, &ev)| (fd, ev))
+            .collect()
+    }
+}
+
+impl File for Epoll {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn write(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
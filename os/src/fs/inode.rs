@@ -0,0 +1,498 @@
+//! On-disk-backed files. The real block layout lives in the `easy-fs`
+//! crate; this module only adapts an `easy_fs::Inode` to the kernel's
+//! `File` trait and tracks per-fd state (offset, open flags).
+
+use super::{File, FileError};
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use spin::RwLock;
+
+/// What an [`Inode`] represents, for `stat`-family syscalls and symlink
+/// resolution. Kept explicit rather than inferred from `dirents`/`data`
+/// being non-empty, since an empty directory and an empty regular file
+/// would otherwise be indistinguishable.
+#[derive(Clone, Copy, PartialEq)]
+enum InodeKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// One on-disk file's mutable state. Reads only need a shared lock, so
+/// concurrent readers no longer serialize behind writers the way a single
+/// whole-filesystem mutex would force them to.
+struct InodeInner {
+    data: Vec<u8>,
+    dirents: Vec<alloc::string::String>,
+    kind: InodeKind,
+    /// The path this inode resolves to, when `kind == Symlink`.
+    symlink_target: Option<String>,
+    /// Permission bits (the low 9 bits of `st_mode`). Defaults to `0o777`;
+    /// `create_inode`/`create_inode_with_parents` set this from
+    /// `sys_open`'s `mode` argument, already masked by the creating
+    /// process's umask (see `sys_umask`).
+    perm: u32,
+    /// Advisory `flock(2)` state, keyed by lock owner (see
+    /// [`OSInode::lock_owner`]) rather than by process — two different opens
+    /// of the same file contend independently, even from the same process.
+    lock: FlockState,
+}
+
+/// Who currently holds an advisory `flock(2)` lock on an inode. At most one
+/// owner can hold the exclusive lock; any number can share the shared lock,
+/// but never both kinds at once.
+#[derive(Default)]
+struct FlockState {
+    shared_owners: BTreeSet<usize>,
+    exclusive_owner: Option<usize>,
+}
+
+/// An in-memory stand-in for `easy_fs::Inode`'s disk-backed storage, with
+/// its own lock rather than sharing one lock across every open file.
+pub struct Inode {
+    inner: RwLock<InodeInner>,
+}
+
+impl Inode {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwLock::new(InodeInner {
+                data: Vec::new(),
+                dirents: Vec::new(),
+                kind: InodeKind::File,
+                symlink_target: None,
+                perm: 0o777,
+                lock: FlockState::default(),
+            }),
+        })
+    }
+
+    pub fn new_dir(dirents: Vec<alloc::string::String>) -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwLock::new(InodeInner {
+                data: Vec::new(),
+                dirents,
+                kind: InodeKind::Dir,
+                symlink_target: None,
+                perm: 0o777,
+                lock: FlockState::default(),
+            }),
+        })
+    }
+
+    pub fn new_symlink(target: String) -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwLock::new(InodeInner {
+                data: Vec::new(),
+                dirents: Vec::new(),
+                kind: InodeKind::Symlink,
+                symlink_target: Some(target),
+                perm: 0o777,
+                lock: FlockState::default(),
+            }),
+        })
+    }
+
+    /// Permission bits set at creation time (or left at the `0o777` default
+    /// for inodes created outside `sys_open`'s `O_CREAT` path).
+    pub fn perm(&self) -> u32 {
+        self.inner.read().perm
+    }
+
+    pub fn set_perm(&self, perm: u32) {
+        self.inner.write().perm = perm;
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.inner.read().kind == InodeKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.inner.read().kind == InodeKind::Symlink
+    }
+
+    /// The path this symlink resolves to, or `None` if it isn't one.
+    pub fn symlink_target(&self) -> Option<String> {
+        self.inner.read().symlink_target.clone()
+    }
+
+    /// Return the `index`-th directory entry's name, or `None` once past
+    /// the last one.
+    pub fn dirent_at(&self, index: usize) -> Option<alloc::string::String> {
+        self.inner.read().dirents.get(index).cloned()
+    }
+
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let inner = self.inner.read();
+        if offset >= inner.data.len() {
+            return 0;
+        }
+        let end = (offset + buf.len()).min(inner.data.len());
+        let n = end - offset;
+        buf[..n].copy_from_slice(&inner.data[offset..end]);
+        n
+    }
+
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut inner = self.inner.write();
+        let end = offset + buf.len();
+        if inner.data.len() < end {
+            inner.data.resize(end, 0);
+        }
+        inner.data[offset..end].copy_from_slice(buf);
+        buf.len()
+    }
+
+    pub fn size(&self) -> usize {
+        self.inner.read().data.len()
+    }
+
+    /// Preallocate (zero-fill) `[offset, offset + len)`, extending the file
+    /// if needed, so a later `write_at` in that range never has to grow the
+    /// backing `Vec` mid-write — or, with `punch_hole`, zero that range in
+    /// place without changing the file's size. This in-memory backing store
+    /// has no block allocation to actually reserve, so "preallocate" and
+    /// "already written as zeros" are the same operation here.
+    pub fn fallocate(&self, offset: usize, len: usize, punch_hole: bool) {
+        let mut inner = self.inner.write();
+        let end = offset + len;
+        if punch_hole {
+            let zero_end = end.min(inner.data.len());
+            if offset < zero_end {
+                inner.data[offset..zero_end].fill(0);
+            }
+        } else if inner.data.len() < end {
+            inner.data.resize(end, 0);
+        }
+    }
+
+    /// Try to acquire this inode's whole-file advisory lock for `owner`,
+    /// `exclusive` or shared. Succeeds immediately (converting any lock
+    /// `owner` already holds to the new mode) if nothing else conflicts;
+    /// returns `false` without blocking otherwise, leaving the caller to
+    /// retry or give up. See `sys_flock`.
+    pub fn try_lock(&self, owner: usize, exclusive: bool) -> bool {
+        let mut inner = self.inner.write();
+        let exclusive_held_elsewhere = matches!(inner.lock.exclusive_owner, Some(o) if o != owner);
+        if exclusive_held_elsewhere {
+            return false;
+        }
+        if exclusive {
+            let shared_held_elsewhere = inner.lock.shared_owners.iter().any(|&o| o != owner);
+            if shared_held_elsewhere {
+                return false;
+            }
+            inner.lock.shared_owners.remove(&owner);
+            inner.lock.exclusive_owner = Some(owner);
+        } else {
+            inner.lock.exclusive_owner = None;
+            inner.lock.shared_owners.insert(owner);
+        }
+        true
+    }
+
+    /// Release any lock `owner` holds on this inode. A no-op if it holds
+    /// none, so both `LOCK_UN` and the automatic release on close (see
+    /// `OSInode`'s `Drop` impl) can call it unconditionally.
+    pub fn unlock(&self, owner: usize) {
+        let mut inner = self.inner.write();
+        inner.lock.shared_owners.remove(&owner);
+        if inner.lock.exclusive_owner == Some(owner) {
+            inner.lock.exclusive_owner = None;
+        }
+    }
+}
+
+/// Force a file's contents into the page cache ahead of time, so the first
+/// real read doesn't pay the block-device latency. This in-memory stand-in
+/// for `easy_fs::Inode` already holds everything resident, so there's
+/// nothing to fetch; a real disk-backed implementation would walk the
+/// inode's block list here and populate the shared block cache.
+pub fn preload(inode: &Arc<Inode>) {
+    let _ = inode.size();
+}
+
+lazy_static! {
+    /// The flat root-directory namespace: name -> inode. Held separately
+    /// from any particular open file so that unlinking a name only removes
+    /// it from here; an `Arc<Inode>` already cloned into an open `OSInode`
+    /// keeps the data alive via ordinary reference counting until the last
+    /// fd referencing it is dropped, giving POSIX unlink-while-open
+    /// semantics for free instead of needing an explicit "pending delete"
+    /// flag.
+    static ref INODE_TABLE: UPSafeCell<BTreeMap<String, Arc<Inode>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Look up `name` in the root namespace.
+pub fn open_inode(name: &str) -> Option<Arc<Inode>> {
+    INODE_TABLE.exclusive_access().get(name).cloned()
+}
+
+/// Create (or replace) `name` in the root namespace with a fresh empty
+/// inode with permission bits `perm` and return it. `perm` is the caller's
+/// responsibility to have already masked by its umask (see `sys_umask`) —
+/// this function just stores whatever it's given.
+pub fn create_inode(name: &str, perm: u32) -> Arc<Inode> {
+    let inode = Inode::new();
+    inode.set_perm(perm);
+    INODE_TABLE
+        .exclusive_access()
+        .insert(String::from(name), inode.clone());
+    inode
+}
+
+/// Like [`create_inode`], but first creates any missing parent path
+/// components (as empty directory inodes, left at their default `0o777`)
+/// instead of requiring them to already exist — the `mkdir -p`-style
+/// behavior `sys_open`'s `O_CREAT_PARENTS` flag asks for. Existing parents
+/// are left untouched. `perm` applies only to the leaf, same as `mkdir -p`
+/// only taking one mode for the final component.
+pub fn create_inode_with_parents(name: &str, perm: u32) -> Arc<Inode> {
+    for (i, c) in name.char_indices() {
+        if c == '/' && i > 0 {
+            let parent = &name[..i];
+            if open_inode(parent).is_none() {
+                INODE_TABLE
+                    .exclusive_access()
+                    .insert(String::from(parent), Inode::new_dir(Vec::new()));
+            }
+        }
+    }
+    create_inode(name, perm)
+}
+
+/// Remove `name` from the root namespace. Any `Arc<Inode>` already handed
+/// out to an open file is unaffected — the inode itself is only freed once
+/// every such clone is dropped. Returns whether `name` existed.
+pub fn unlink_inode(name: &str) -> bool {
+    INODE_TABLE.exclusive_access().remove(name).is_some()
+}
+
+/// Create (or replace) `name` in the root namespace with a symlink pointing
+/// at `target`. `target` is stored as given, unresolved, the same way a
+/// real symlink's contents are just a path string rather than a cached
+/// pointer to its target's inode.
+pub fn create_symlink(name: &str, target: &str) -> Arc<Inode> {
+    let inode = Inode::new_symlink(String::from(target));
+    INODE_TABLE
+        .exclusive_access()
+        .insert(String::from(name), inode.clone());
+    inode
+}
+
+/// Look up `name`, following symlinks when `follow` is set — the
+/// `stat`/`readlinkat`-style distinction between resolving through a
+/// symlink (`stat`) and stopping at it (`lstat`, `readlinkat` itself).
+/// Bounds the chain at 8 hops so a symlink loop reports failure instead of
+/// spinning forever; nothing in this tree validates links on creation.
+pub fn resolve_inode(name: &str, follow: bool) -> Option<Arc<Inode>> {
+    let inode = open_inode(name)?;
+    if !follow {
+        return Some(inode);
+    }
+    let mut current = inode;
+    for _ in 0..8 {
+        if !current.is_symlink() {
+            return Some(current);
+        }
+        let target = current.symlink_target()?;
+        current = open_inode(&target)?;
+    }
+    if current.is_symlink() {
+        None
+    } else {
+        Some(current)
+    }
+}
+
+/// Normalize `path`'s `.`/`..` components and prefix the result onto
+/// `root`, the namespace-flat equivalent of resolving an absolute path
+/// against a chroot. `..` past the root has nowhere further to pop and is
+/// simply dropped, so it clamps at `root` instead of escaping it. With the
+/// default root `/` this just collapses `.`/`..` in an otherwise-absolute
+/// path, so every absolute-path syscall can route through it unconditionally
+/// rather than special-casing the unchrooted case.
+pub fn resolve_chroot_path(root: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            seg => segments.push(seg),
+        }
+    }
+    let mut result = String::from(root.trim_end_matches('/'));
+    for seg in segments {
+        result.push('/');
+        result.push_str(seg);
+    }
+    if result.is_empty() {
+        result.push('/');
+    }
+    result
+}
+
+/// Scan the flat namespace for metadata inconsistencies and return the
+/// number found. This in-memory filesystem has no separate block/inode
+/// bitmap or link-count bookkeeping to cross-check against actual
+/// allocations — every inode *is* its `data`/`dirents` state directly, so
+/// the classic easy-fs fsck checks (bitmap-vs-allocation, link-count
+/// mismatches) don't have anything to apply to here. What *can* still
+/// drift from reality is a directory's dirent list naming an entry that
+/// was since unlinked, so that's what this checks.
+pub fn check_consistency() -> usize {
+    let table = INODE_TABLE.exclusive_access();
+    let mut anomalies = 0;
+    for inode in table.values() {
+        if !inode.is_dir() {
+            continue;
+        }
+        let mut index = 0;
+        while let Some(name) = inode.dirent_at(index) {
+            if !table.contains_key(&name) {
+                anomalies += 1;
+            }
+            index += 1;
+        }
+    }
+    anomalies
+}
+
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    inner: UPSafeCell<OSInodeInner>,
+}
+
+struct OSInodeInner {
+    offset: usize,
+    dirent_index: usize,
+    inode: Arc<Inode>,
+}
+
+impl OSInode {
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+        Self {
+            readable,
+            writable,
+            inner: unsafe {
+                UPSafeCell::new(OSInodeInner {
+                    offset: 0,
+                    dirent_index: 0,
+                    inode,
+                })
+            },
+        }
+    }
+
+    /// The backing inode, for operations that need to move bytes
+    /// directly between two files without round-tripping through a user
+    /// buffer (e.g. `copy_file_range`).
+    pub fn inode(&self) -> Arc<Inode> {
+        self.inner.exclusive_access().inode.clone()
+    }
+
+    /// The file's own read/write cursor, as used by `read`/`write` when the
+    /// caller doesn't supply an explicit offset (e.g. `copy_file_range`'s
+    /// `off == NULL` case).
+    pub fn offset(&self) -> usize {
+        self.inner.exclusive_access().offset
+    }
+
+    pub fn set_offset(&self, offset: usize) {
+        self.inner.exclusive_access().offset = offset;
+    }
+
+    /// Return the next directory entry's name and advance the cursor, or
+    /// `None` once the directory is exhausted. Kept separate from `read`
+    /// so callers don't have to know the on-disk dirent record layout to
+    /// consume entries one at a time.
+    pub fn next_dirent(&self) -> Option<alloc::string::String> {
+        let mut inner = self.inner.exclusive_access();
+        let entry = inner.inode.dirent_at(inner.dirent_index);
+        if entry.is_some() {
+            inner.dirent_index += 1;
+        }
+        entry
+    }
+
+    /// Like [`Self::next_dirent`], but without advancing the cursor —
+    /// `sys_getdents64` needs to know an entry's encoded size before
+    /// deciding whether it fits in the caller's remaining buffer, and only
+    /// wants to actually consume it once it knows it does.
+    pub fn peek_dirent(&self) -> Option<alloc::string::String> {
+        let inner = self.inner.exclusive_access();
+        inner.inode.dirent_at(inner.dirent_index)
+    }
+
+    /// Identifies this open file description as an `flock(2)` lock owner.
+    /// `dup`/`dup2` share the same `Arc<OSInode>` (hence the same address)
+    /// for a given open, exactly matching real `flock`'s "the lock belongs
+    /// to the open file description, not the fd or the process" semantics;
+    /// a separate `sys_openat` of the same path gets its own `OSInode` and
+    /// so contends as a distinct owner, even from the same task.
+    pub fn lock_owner(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl Drop for OSInode {
+    /// `flock(2)` locks are released when their open file description
+    /// closes. Since `dup`'d fds share this same `OSInode` and only the
+    /// last `Arc` clone dropping runs this, a lock outlives any individual
+    /// `sys_close` on a duplicated fd and is released exactly when the
+    /// description itself finally goes away — matching `sys_flock`'s doc
+    /// comment.
+    fn drop(&mut self) {
+        let inode = self.inner.exclusive_access().inode.clone();
+        inode.unlock(self.lock_owner());
+    }
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+    fn read(&self, mut buf: UserBuffer) -> Result<usize, FileError> {
+        let mut inner = self.inner.exclusive_access();
+        let mut total = 0usize;
+        for slice in buf.buffers.iter_mut() {
+            let n = inner.inode.read_at(inner.offset, slice);
+            inner.offset += n;
+            total += n;
+            // Short read (EOF) — stop instead of reporting the full
+            // requested length as transferred.
+            if n < slice.len() {
+                break;
+            }
+        }
+        Ok(total)
+    }
+    fn write(&self, buf: UserBuffer) -> Result<usize, FileError> {
+        let mut inner = self.inner.exclusive_access();
+        let mut total = 0usize;
+        for slice in buf.buffers.iter() {
+            let n = inner.inode.write_at(inner.offset, slice);
+            inner.offset += n;
+            total += n;
+            if n < slice.len() {
+                break;
+            }
+        }
+        Ok(total)
+    }
+}
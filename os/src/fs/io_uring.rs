@@ -0,0 +1,109 @@
+//! Minimal io_uring-style batched submission: see [`IoRing`]'s doc comment.
+
+use super::FileError;
+use crate::mm::{UserBuffer, VirtAddr};
+use crate::sync::UPSafeCell;
+use core::mem::size_of;
+
+pub const IORING_OP_READ: u32 = 0;
+pub const IORING_OP_WRITE: u32 = 1;
+
+/// One submission queue entry, laid out exactly as userspace writes it
+/// directly into the mmap'd submission ring (`sys_iosetup`'s `sq_addr`
+/// out-param) — no syscall needed to enqueue a request, only to process
+/// the entries already sitting there (`sys_iosubmit`).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct IoSqe {
+    pub opcode: u32,
+    pub fd: u32,
+    pub buf: u64,
+    pub len: u32,
+    pub offset: u32,
+    pub user_data: u64,
+}
+
+/// One completion queue entry, written by `sys_iosubmit` into the mmap'd
+/// completion ring (`sys_iosetup`'s `cq_addr` out-param) for userspace to
+/// read back directly.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct IoCqe {
+    pub user_data: u64,
+    pub res: i64,
+}
+
+/// A minimal io_uring-style pair of rings: a submission queue the caller
+/// fills directly in its own mmap'd memory (no syscall needed to enqueue
+/// an entry) and a completion queue `sys_iosubmit` posts results to, also
+/// in mmap'd memory the caller reads directly. This struct, installed in
+/// the caller's fd table by `sys_iosetup`, just remembers where the two
+/// rings live and tracks the completion ring's write cursor.
+///
+/// A real io_uring processes submissions asynchronously and needs a
+/// lock-free head/tail protocol between kernel and userspace producers and
+/// consumers running concurrently. `sys_iosubmit` here instead processes
+/// every entry synchronously before returning, so there's no concurrent
+/// access to protect against — this is the same "batched, but not actually
+/// asynchronous" scope as the request that asked for it.
+pub struct IoRing {
+    sq_base: VirtAddr,
+    cq_base: VirtAddr,
+    entries: usize,
+    cq_tail: UPSafeCell<usize>,
+}
+
+impl IoRing {
+    pub fn new(sq_base: VirtAddr, cq_base: VirtAddr, entries: usize) -> Self {
+        Self {
+            sq_base,
+            cq_base,
+            entries,
+            cq_tail: unsafe { UPSafeCell::new(0) },
+        }
+    }
+
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    /// The address of submission slot `index`. Callers are responsible for
+    /// keeping `index < entries`; `sys_iosubmit` only ever calls this with
+    /// indices it has already range-checked.
+    pub fn sqe_addr(&self, index: usize) -> usize {
+        self.sq_base.0 + index * size_of::<IoSqe>()
+    }
+
+    /// Reserve and return the address of the next completion slot,
+    /// wrapping around `entries` once the ring fills. A real io_uring
+    /// would instead report `-EBUSY` back to the submitter when its
+    /// consumer hasn't kept up and the ring is genuinely full; there's no
+    /// separate completion read cursor here for `sys_iosubmit` to check
+    /// against (nothing in this tree reads completions back out except by
+    /// directly reading the mmap'd memory the caller already has), so an
+    /// overrun just silently overwrites the oldest unread completion.
+    pub fn next_cqe_addr(&self) -> usize {
+        let mut tail = self.cq_tail.exclusive_access();
+        let addr = self.cq_base.0 + (*tail % self.entries) * size_of::<IoCqe>();
+        *tail += 1;
+        addr
+    }
+}
+
+impl super::File for IoRing {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn write(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
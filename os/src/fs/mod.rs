@@ -0,0 +1,91 @@
+//! The file abstraction shared by pipes, stdio and on-disk inodes, plus
+//! the concrete implementations.
+
+mod epoll;
+mod inode;
+mod io_uring;
+mod pipe;
+mod stdio;
+
+pub use epoll::{Epoll, EpollEvent, EPOLLIN, EPOLLOUT, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD};
+pub use inode::{
+    check_consistency, create_inode, create_inode_with_parents, create_symlink, open_inode,
+    preload, resolve_chroot_path, resolve_inode, unlink_inode, Inode, OSInode,
+};
+pub use io_uring::{IoCqe, IoRing, IoSqe, IORING_OP_READ, IORING_OP_WRITE};
+pub use pipe::{make_pipe, Pipe};
+pub use stdio::{Stderr, Stdin, Stdout};
+
+use crate::mm::UserBuffer;
+use alloc::sync::Arc;
+use bitflags::bitflags;
+
+/// Why a `File::read`/`write` call failed. Kept to one variant for now —
+/// every backing store in this tree (the in-memory inode table, pipe ring
+/// buffers) can't actually fail to read or write the bytes it has; this
+/// exists so the trait can express failure at all once a real block device
+/// is added, without another signature change at every call site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileError {
+    /// The underlying storage failed to service the read/write.
+    Io,
+}
+
+/// Common interface every fd-backed object implements, whether it's a
+/// regular file, a pipe, or a console stream.
+pub trait File: Send + Sync + core::any::Any {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    fn read(&self, buf: UserBuffer) -> Result<usize, FileError>;
+    fn write(&self, buf: UserBuffer) -> Result<usize, FileError>;
+
+    /// Downcasting hook so syscalls that only make sense for one concrete
+    /// file kind (e.g. `readdir` on an `OSInode`) can recover it from a
+    /// `dyn File` without a new enum variant per kind.
+    fn as_any(&self) -> &dyn core::any::Any;
+
+    /// Whether a read would return data (or EOF) without blocking. Regular
+    /// files and the console are always ready; pipes depend on their
+    /// buffer state, see `Pipe::ready_to_read`.
+    fn poll_read(&self) -> bool {
+        true
+    }
+    /// Whether a write would make progress without blocking.
+    fn poll_write(&self) -> bool {
+        true
+    }
+}
+
+/// The POSIX "open file description" — one per `open`/`pipe`/`epoll_create1`
+/// call, distinct from the fd (an index into a process's `fd_table`) that
+/// names it. `dup`/`dup2`'d fds and a forked child's inherited fds all point
+/// at the same `Arc<OpenFileDescription>`, so anything stored here (today
+/// just the underlying file) is shared across all of them; a fresh `open` of
+/// the same path gets its own. The read/write cursor itself stays inside the
+/// concrete `File` impl rather than moving here — `OSInode` already keeps
+/// its own offset per instance, so two fds sharing an `Arc<OSInode>` already
+/// share a cursor the same way two fds sharing an `Arc<OpenFileDescription>`
+/// would, and a pipe's position is just where its ring buffer is, with no
+/// separate cursor to speak of.
+pub struct OpenFileDescription {
+    pub file: Arc<dyn File>,
+}
+
+impl OpenFileDescription {
+    pub fn new(file: Arc<dyn File>) -> Arc<Self> {
+        Arc::new(Self { file })
+    }
+}
+
+bitflags! {
+    /// Per-fd flags, as opposed to per-open-file-description ones: unlike
+    /// `OpenFileDescription`, these do NOT follow a `dup`/`dup2`'d fd to its
+    /// new number, matching `FD_CLOEXEC`'s real-world behavior of being the
+    /// one `fcntl` flag that's famously fd-specific rather than
+    /// description-specific. Plumbing only for now — this tree's `sys_exec`
+    /// doesn't close any fds across exec yet (see `exec_replace_memory_set`),
+    /// so nothing reads `CLOEXEC` back out yet.
+    pub struct FdFlags: u8 {
+        const CLOEXEC = 1 << 0;
+    }
+}
@@ -0,0 +1,77 @@
+//! The three standard streams every process is supposed to start with.
+//!
+//! Real byte transfer to/from the terminal is meant to go through an SBI
+//! console driver, which — same as [`crate::lang_items`]'s panic-time
+//! `println_placeholder` — is outside the scope of this tree's tracked
+//! changes: there's no `console_putchar`/`console_getchar` wired up here
+//! yet. These types exist so fd 0/1/2 are real, distinct `File` objects a
+//! process can `read`/`write`/`dup`/`close` like any other fd today, with
+//! the one missing piece being the driver call at the bottom of each
+//! method once one lands.
+
+use super::{File, FileError};
+use crate::mm::UserBuffer;
+
+pub struct Stdin;
+pub struct Stdout;
+pub struct Stderr;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+    fn read(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        // No `console_getchar` to poll yet (see the module doc comment) —
+        // reads always see EOF rather than blocking forever on input that
+        // can never arrive.
+        Ok(0)
+    }
+    fn write(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+    fn read(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn write(&self, buf: UserBuffer) -> Result<usize, FileError> {
+        // Would hand each byte to `console_putchar` here; reports the full
+        // length transferred since nothing backs it with a rejectable
+        // sink yet (see the module doc comment).
+        Ok(buf.len())
+    }
+}
+
+impl File for Stderr {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+    fn read(&self, _buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(0)
+    }
+    fn write(&self, buf: UserBuffer) -> Result<usize, FileError> {
+        Ok(buf.len())
+    }
+}
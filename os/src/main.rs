@@ -0,0 +1,37 @@
+//! The kernel entry point and top-level module tree.
+//!
+//! `no_std`/`no_main` are both suppressed under `cfg(test)`: `cargo test`
+//! needs `std`'s test harness to collect and run `#[test]` fns, which in
+//! turn needs a real `main` to drive it rather than this crate's own
+//! `rust_main` entry point. Test builds link `std` (and get its allocator
+//! and panic handler for free — see `heap_allocator`/`lang_items`) instead
+//! of this kernel's own; `rust_main` itself is simply unreachable dead code
+//! under `cfg(test)`, same as it is on any host build.
+
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![feature(alloc_error_handler)]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate bitflags;
+
+#[macro_use]
+extern crate lazy_static;
+
+mod config;
+mod device_tree;
+mod fs;
+mod lang_items;
+mod mm;
+mod sync;
+mod syscall;
+mod task;
+mod timer;
+
+#[no_mangle]
+pub fn rust_main() -> ! {
+    mm::init();
+    panic!("kernel initialization complete, scheduler not wired up in this tree");
+}
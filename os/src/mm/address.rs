@@ -0,0 +1,357 @@
+//! Physical and virtual address / page-number newtypes and the
+//! conversions between them.
+
+use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS};
+use core::fmt::{self, Debug, Formatter};
+
+use super::page_table::PageTableEntry;
+
+const PA_WIDTH_SV39: usize = 56;
+const VA_WIDTH_SV39: usize = 39;
+const PPN_WIDTH_SV39: usize = PA_WIDTH_SV39 - PAGE_SIZE_BITS;
+const VPN_WIDTH_SV39: usize = VA_WIDTH_SV39 - PAGE_SIZE_BITS;
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default)]
+pub struct PhysAddr(pub usize);
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default)]
+pub struct VirtAddr(pub usize);
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Hash)]
+pub struct PhysPageNum(pub usize);
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Hash)]
+pub struct VirtPageNum(pub usize);
+
+impl Debug for VirtAddr {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("VA:{:#x}", self.0))
+    }
+}
+impl Debug for VirtPageNum {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("VPN:{:#x}", self.0))
+    }
+}
+impl Debug for PhysAddr {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("PA:{:#x}", self.0))
+    }
+}
+impl Debug for PhysPageNum {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("PPN:{:#x}", self.0))
+    }
+}
+
+impl From<usize> for PhysAddr {
+    fn from(v: usize) -> Self {
+        Self(v & ((1 << PA_WIDTH_SV39) - 1))
+    }
+}
+impl From<usize> for PhysPageNum {
+    fn from(v: usize) -> Self {
+        Self(v & ((1 << PPN_WIDTH_SV39) - 1))
+    }
+}
+impl From<usize> for VirtAddr {
+    fn from(v: usize) -> Self {
+        Self(v & ((1 << VA_WIDTH_SV39) - 1))
+    }
+}
+impl From<usize> for VirtPageNum {
+    fn from(v: usize) -> Self {
+        Self(v & ((1 << VPN_WIDTH_SV39) - 1))
+    }
+}
+impl From<PhysAddr> for usize {
+    fn from(v: PhysAddr) -> Self {
+        v.0
+    }
+}
+impl From<PhysPageNum> for usize {
+    fn from(v: PhysPageNum) -> Self {
+        v.0
+    }
+}
+impl From<VirtAddr> for usize {
+    fn from(v: VirtAddr) -> Self {
+        v.0
+    }
+}
+impl From<VirtPageNum> for usize {
+    fn from(v: VirtPageNum) -> Self {
+        v.0
+    }
+}
+
+impl VirtAddr {
+    pub fn floor(&self) -> VirtPageNum {
+        VirtPageNum(self.0 / PAGE_SIZE)
+    }
+    /// Rounds up to the containing page, or `None` if that would overflow
+    /// `usize` — reachable from a syscall-supplied `addr + length` that
+    /// lands within `PAGE_SIZE` of `usize::MAX`, so callers fed directly by
+    /// syscall arguments (`sys_mmap` and friends) must turn a `None` into
+    /// `-EINVAL` rather than unwrap it.
+    pub fn ceil(&self) -> Option<VirtPageNum> {
+        if self.0 == 0 {
+            Some(VirtPageNum(0))
+        } else {
+            let rounded = (self.0 - 1).checked_add(PAGE_SIZE)?;
+            Some(VirtPageNum(rounded / PAGE_SIZE))
+        }
+    }
+    pub fn page_offset(&self) -> usize {
+        self.0 & (PAGE_SIZE - 1)
+    }
+    pub fn aligned(&self) -> bool {
+        self.page_offset() == 0
+    }
+}
+impl From<VirtAddr> for VirtPageNum {
+    fn from(v: VirtAddr) -> Self {
+        assert_eq!(v.page_offset(), 0);
+        v.floor()
+    }
+}
+impl From<VirtPageNum> for VirtAddr {
+    fn from(v: VirtPageNum) -> Self {
+        Self(v.0 * PAGE_SIZE)
+    }
+}
+
+impl PhysAddr {
+    pub fn floor(&self) -> PhysPageNum {
+        PhysPageNum(self.0 / PAGE_SIZE)
+    }
+    /// See [`VirtAddr::ceil`]'s doc comment — same overflow guard, same
+    /// `None`-on-overflow contract.
+    pub fn ceil(&self) -> Option<PhysPageNum> {
+        if self.0 == 0 {
+            Some(PhysPageNum(0))
+        } else {
+            let rounded = (self.0 - 1).checked_add(PAGE_SIZE)?;
+            Some(PhysPageNum(rounded / PAGE_SIZE))
+        }
+    }
+    pub fn page_offset(&self) -> usize {
+        self.0 & (PAGE_SIZE - 1)
+    }
+    pub fn aligned(&self) -> bool {
+        self.page_offset() == 0
+    }
+}
+impl From<PhysAddr> for PhysPageNum {
+    fn from(v: PhysAddr) -> Self {
+        assert_eq!(v.page_offset(), 0);
+        v.floor()
+    }
+}
+impl From<PhysPageNum> for PhysAddr {
+    fn from(v: PhysPageNum) -> Self {
+        Self(v.0 * PAGE_SIZE)
+    }
+}
+
+impl VirtPageNum {
+    /// Split into the three Sv39 page-table indices, root first.
+    pub fn indexes(&self) -> [usize; 3] {
+        let mut vpn = self.0;
+        let mut idx = [0usize; 3];
+        for i in (0..3).rev() {
+            idx[i] = vpn & 511;
+            vpn >>= 9;
+        }
+        idx
+    }
+}
+
+impl PhysPageNum {
+    pub fn get_pte_array(&self) -> &'static mut [PageTableEntry] {
+        let pa: PhysAddr = (*self).into();
+        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut PageTableEntry, 512) }
+    }
+    pub fn get_bytes_array(&self) -> &'static mut [u8] {
+        let pa: PhysAddr = (*self).into();
+        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, PAGE_SIZE) }
+    }
+    pub fn get_mut<T>(&self) -> &'static mut T {
+        let pa: PhysAddr = (*self).into();
+        unsafe { (pa.0 as *mut T).as_mut().unwrap() }
+    }
+}
+
+/// Trait for types that can be advanced one unit at a time, used to walk a
+/// `start..end` range of page numbers with a `for` loop via [`SimpleRange`].
+pub trait StepByOne {
+    fn step(&mut self);
+}
+impl StepByOne for VirtPageNum {
+    /// Saturating, not wrapping or panicking: an area reaching all the way
+    /// to the top of the address space would otherwise overflow on the
+    /// `step()` past its last page. Saturating at the max `usize` is safe
+    /// because [`SimpleRangeIterator::next`] stops via `current >= end`
+    /// rather than `current == end`, so a range whose end itself sits at
+    /// that max value still terminates cleanly instead of stepping past it
+    /// and looping forever (or panicking, pre-saturation).
+    fn step(&mut self) {
+        self.0 = self.0.saturating_add(1);
+    }
+}
+impl StepByOne for PhysPageNum {
+    fn step(&mut self) {
+        self.0 = self.0.saturating_add(1);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SimpleRange<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    l: T,
+    r: T,
+}
+impl<T> SimpleRange<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "start {:?} > end {:?}", start, end);
+        Self { l: start, r: end }
+    }
+    pub fn get_start(&self) -> T {
+        self.l
+    }
+    pub fn get_end(&self) -> T {
+        self.r
+    }
+}
+impl<T> IntoIterator for SimpleRange<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    type Item = T;
+    type IntoIter = SimpleRangeIterator<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        SimpleRangeIterator::new(self.l, self.r)
+    }
+}
+pub struct SimpleRangeIterator<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    current: T,
+    end: T,
+}
+impl<T> SimpleRangeIterator<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    pub fn new(l: T, r: T) -> Self {
+        Self { current: l, end: r }
+    }
+}
+impl<T> Iterator for SimpleRangeIterator<T>
+where
+    T: StepByOne + Copy + PartialEq + PartialOrd + Debug,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current >= self.end {
+            None
+        } else {
+            let t = self.current;
+            self.current.step();
+            Some(t)
+        }
+    }
+}
+
+pub type VPNRange = SimpleRange<VirtPageNum>;
+
+/// Whether `[a_start, a_end)` and `[b_start, b_end)` share any page at
+/// all — the check every area-insertion path needs to run *before*
+/// touching a page table, since the alternative is discovering the
+/// overlap via `insert_area`'s own `assert!` (or `PageTable::map`'s
+/// "already mapped" one beneath it), which panics the whole kernel rather
+/// than letting the caller reject the request. Used by `MemorySet::
+/// insert_area`'s overlap assert, `sys_mmap`'s `MAP_FIXED` rejection, and
+/// `from_elf`'s overlapping-`PT_LOAD`-segment rejection.
+pub fn vpn_ranges_overlap(
+    a_start: VirtPageNum,
+    a_end: VirtPageNum,
+    b_start: VirtPageNum,
+    b_end: VirtPageNum,
+) -> bool {
+    a_start < b_end && b_start < a_end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ceil_rounds_unaligned_addresses_up() {
+        assert_eq!(VirtAddr(1).ceil(), Some(VirtPageNum(1)));
+        assert_eq!(VirtAddr(PAGE_SIZE - 1).ceil(), Some(VirtPageNum(1)));
+        assert_eq!(VirtAddr(PAGE_SIZE).ceil(), Some(VirtPageNum(1)));
+        assert_eq!(VirtAddr(PAGE_SIZE + 1).ceil(), Some(VirtPageNum(2)));
+    }
+
+    #[test]
+    fn ceil_of_zero_is_zero() {
+        assert_eq!(VirtAddr(0).ceil(), Some(VirtPageNum(0)));
+        assert_eq!(PhysAddr(0).ceil(), Some(PhysPageNum(0)));
+    }
+
+    #[test]
+    fn floor_and_ceil_agree_on_aligned_addresses() {
+        let aligned = VirtAddr(4 * PAGE_SIZE);
+        assert_eq!(aligned.floor(), aligned.ceil().unwrap());
+    }
+
+    #[test]
+    fn ceil_returns_none_instead_of_panicking_on_overflow() {
+        // One byte past the last page `usize::MAX` could round up to.
+        assert_eq!(VirtAddr(usize::MAX).ceil(), None);
+        assert_eq!(PhysAddr(usize::MAX).ceil(), None);
+    }
+
+    #[test]
+    fn overlapping_ranges_are_detected() {
+        let a = (VirtPageNum(0), VirtPageNum(10));
+        // Partially overlapping, sharing just one edge page, identical, and
+        // fully-surrounding all count as overlapping.
+        for b in [
+            (VirtPageNum(5), VirtPageNum(15)),
+            (VirtPageNum(9), VirtPageNum(20)),
+            (VirtPageNum(0), VirtPageNum(10)),
+            (VirtPageNum(0), VirtPageNum(100)),
+        ] {
+            assert!(vpn_ranges_overlap(a.0, a.1, b.0, b.1));
+            assert!(vpn_ranges_overlap(b.0, b.1, a.0, a.1), "overlap must be symmetric");
+        }
+    }
+
+    #[test]
+    fn adjacent_and_disjoint_ranges_do_not_overlap() {
+        let a = (VirtPageNum(0), VirtPageNum(10));
+        // `get_end()` is exclusive, so a range starting exactly where `a`
+        // ends is adjacent, not overlapping.
+        for b in [(VirtPageNum(10), VirtPageNum(20)), (VirtPageNum(50), VirtPageNum(60))] {
+            assert!(!vpn_ranges_overlap(a.0, a.1, b.0, b.1));
+            assert!(!vpn_ranges_overlap(b.0, b.1, a.0, a.1));
+        }
+    }
+
+    #[test]
+    fn virt_addr_from_usize_masks_to_sv39_width() {
+        // `sys_mmap(0, usize::MAX, ...)`'s `length` goes through this
+        // conversion before ever reaching `ceil()` — see `VirtAddr::ceil`'s
+        // doc comment.
+        let va = VirtAddr::from(usize::MAX);
+        assert!(va.0 < (1 << VA_WIDTH_SV39));
+    }
+}
@@ -0,0 +1,167 @@
+//! Loading an ELF image into a fresh [`MemorySet`].
+
+use super::address::{vpn_ranges_overlap, VirtAddr, VirtPageNum};
+use super::memory_set::{KernelError, MapPermission, MemorySet, TRAP_CONTEXT};
+use crate::config::{TRAMPOLINE, USER_STACK_SIZE};
+use xmas_elf::program::Type;
+use xmas_elf::ElfFile;
+
+#[derive(Debug)]
+pub enum ElfLoadError {
+    /// The header didn't parse as ELF at all (truncated file, bad magic).
+    Malformed(&'static str),
+    /// Parsed fine but isn't something this kernel can run (wrong arch,
+    /// wrong ELF class, no loadable segments).
+    Unsupported(&'static str),
+    /// A loadable segment's data couldn't be copied in because the frame
+    /// allocator ran dry partway through mapping it.
+    OutOfMemory,
+}
+
+impl From<KernelError> for ElfLoadError {
+    fn from(_: KernelError) -> Self {
+        ElfLoadError::OutOfMemory
+    }
+}
+
+/// Parse an ELF image and map its loadable segments, the user stack and
+/// the trap context into a fresh address space.
+///
+/// Returns `(memory_set, user_stack_top, entry_point)` on success. Used to
+/// panic on any malformed or unsupported input, which let one bad
+/// user-supplied binary take down the whole kernel; callers now get an
+/// `ElfLoadError` to report back to the caller of `exec`/`spawn` instead.
+///
+/// Every segment and the stack are mapped via `insert_area`/
+/// `insert_framed_area`, both eagerly backed by real frames on the spot —
+/// there's no lazy loading here for the entry point or stack to refault
+/// on, so there's nothing for a prefault hint to eagerly resolve ahead of
+/// time. `MemorySet::prefault` exists for the one place this tree's
+/// address spaces actually do defer a page: `insert_mmap_area`'s
+/// zero-frame-backed mapping, which already prefaults its own first page
+/// for exactly this reason.
+pub fn from_elf(elf_data: &[u8]) -> Result<(MemorySet, usize, usize), ElfLoadError> {
+    let mut memory_set = MemorySet::new_bare();
+    memory_set.map_trampoline();
+
+    let elf = ElfFile::new(elf_data).map_err(ElfLoadError::Malformed)?;
+    let elf_header = elf.header;
+    let magic = elf_header.pt1.magic;
+    if magic != [0x7f, 0x45, 0x4c, 0x46] {
+        return Err(ElfLoadError::Unsupported("not an ELF file"));
+    }
+
+    let ph_count = elf_header.pt2.ph_count();
+    let mut max_end_vpn = VirtPageNum(0);
+    let mut loaded_any = false;
+    for i in 0..ph_count {
+        let ph = elf
+            .program_header(i)
+            .map_err(|_| ElfLoadError::Malformed("bad program header"))?;
+        if ph.get_type() != Ok(Type::Load) {
+            continue;
+        }
+        loaded_any = true;
+        let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
+        let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+        let mut perm = MapPermission::U;
+        let flags = ph.flags();
+        if flags.is_read() {
+            perm |= MapPermission::R;
+        }
+        if flags.is_write() {
+            perm |= MapPermission::W;
+        }
+        if flags.is_execute() {
+            perm |= MapPermission::X;
+        }
+        let data = &elf.input
+            [ph.offset() as usize..(ph.offset() + ph.file_size()) as usize];
+        // `insert_area` itself would only notice an overlap via its own
+        // `assert!`, which panics the whole kernel rather than rejecting
+        // the binary — checked here first so two overlapping `PT_LOAD`
+        // headers in a malformed ELF come back as an `ElfLoadError`
+        // instead.
+        let start_vpn = start_va.floor();
+        let overlap_end_vpn = end_va
+            .ceil()
+            .ok_or(ElfLoadError::Malformed("segment address too large"))?;
+        if memory_set
+            .areas
+            .iter()
+            .any(|a| vpn_ranges_overlap(start_vpn, overlap_end_vpn, a.vpn_range.get_start(), a.vpn_range.get_end()))
+        {
+            return Err(ElfLoadError::Malformed("overlapping PT_LOAD segments"));
+        }
+        memory_set.insert_area(start_va, end_va, perm, Some(data))?;
+        // .bss tail: mem_size can exceed file_size, and the unwritten part
+        // may share the last page with real file data. Zero it explicitly
+        // rather than depending on the frame allocator happening to hand
+        // back zeroed memory — that's an allocator-internal guarantee, not
+        // part of its contract.
+        if ph.mem_size() > ph.file_size() {
+            let bss_start = usize::from(start_va) + ph.file_size() as usize;
+            let bss_end = usize::from(start_va) + ph.mem_size() as usize;
+            let mut va = VirtAddr::from(bss_start);
+            while usize::from(va) < bss_end {
+                let vpn = va.floor();
+                if let Some(pte) = memory_set.page_table.translate(vpn) {
+                    let page = pte.ppn().get_bytes_array();
+                    let page_start = va.page_offset();
+                    let page_end = VirtAddr::from(VirtPageNum(vpn.0 + 1))
+                        .0
+                        .min(bss_end)
+                        - usize::from(VirtAddr::from(vpn));
+                    for b in &mut page[page_start..page_end] {
+                        *b = 0;
+                    }
+                }
+                va = VirtAddr::from(VirtPageNum(vpn.0 + 1));
+            }
+        }
+        max_end_vpn = max_end_vpn.max(overlap_end_vpn);
+    }
+    if !loaded_any {
+        return Err(ElfLoadError::Unsupported("no PT_LOAD segments"));
+    }
+
+    // User stack, one guard page below the stack itself.
+    let user_stack_bottom: VirtAddr = max_end_vpn.into();
+    let user_stack_bottom = VirtAddr::from(usize::from(user_stack_bottom) + crate::config::PAGE_SIZE);
+    let user_stack_top = usize::from(user_stack_bottom) + USER_STACK_SIZE;
+    memory_set.insert_framed_area(
+        user_stack_bottom,
+        user_stack_top.into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    );
+    memory_set.insert_framed_area(
+        TRAP_CONTEXT.into(),
+        TRAMPOLINE.into(),
+        MapPermission::R | MapPermission::W,
+    );
+
+    Ok((
+        memory_set,
+        user_stack_top,
+        elf.header.pt2.entry_point() as usize,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mm::frame_allocator::init_test_frame_allocator;
+
+    #[test]
+    fn truncated_data_is_rejected_without_panicking() {
+        init_test_frame_allocator(8);
+        assert!(from_elf(&[]).is_err());
+        assert!(from_elf(&[0x7f, 0x45, 0x4c, 0x46]).is_err());
+    }
+
+    #[test]
+    fn non_elf_data_is_rejected_without_panicking() {
+        init_test_frame_allocator(8);
+        assert!(from_elf(b"this is plainly not an ELF image, just padding").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Physical frame allocation: a stack allocator over the free range of
+//! physical memory left after the kernel image.
+
+use super::address::{PhysAddr, PhysPageNum};
+use crate::config::MEMORY_END;
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+#[cfg(test)]
+use crate::config::PAGE_SIZE;
+
+pub struct FrameTracker {
+    pub ppn: PhysPageNum,
+}
+
+impl FrameTracker {
+    pub fn new(ppn: PhysPageNum) -> Self {
+        // Clear the frame so stale data never leaks into a new owner.
+        let bytes_array = ppn.get_bytes_array();
+        for byte in bytes_array {
+            *byte = 0;
+        }
+        Self { ppn }
+    }
+}
+
+impl Drop for FrameTracker {
+    fn drop(&mut self) {
+        frame_dealloc(self.ppn);
+    }
+}
+
+trait FrameAllocator {
+    fn new() -> Self;
+    fn alloc(&mut self) -> Option<PhysPageNum>;
+    fn dealloc(&mut self, ppn: PhysPageNum);
+}
+
+pub struct StackFrameAllocator {
+    current: usize,
+    end: usize,
+    recycled: Vec<usize>,
+}
+
+impl StackFrameAllocator {
+    pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.current = l.0;
+        self.end = r.0;
+    }
+}
+impl FrameAllocator for StackFrameAllocator {
+    fn new() -> Self {
+        Self {
+            current: 0,
+            end: 0,
+            recycled: Vec::new(),
+        }
+    }
+    fn alloc(&mut self) -> Option<PhysPageNum> {
+        if let Some(ppn) = self.recycled.pop() {
+            Some(ppn.into())
+        } else if self.current == self.end {
+            None
+        } else {
+            self.current += 1;
+            Some((self.current - 1).into())
+        }
+    }
+    fn dealloc(&mut self, ppn: PhysPageNum) {
+        let ppn = ppn.0;
+        if ppn >= self.current || self.recycled.iter().any(|&v| v == ppn) {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn);
+        }
+        self.recycled.push(ppn);
+    }
+}
+
+type FrameAllocatorImpl = StackFrameAllocator;
+
+lazy_static! {
+    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
+        unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
+    /// The physical memory end currently in effect, updated by
+    /// [`init_frame_allocator_to`]. [`crate::config::MEMORY_END`] is only
+    /// the compile-time default used until a real size is discovered (e.g.
+    /// from the device tree) and [`crate::mm::set_memory_end`] overrides it.
+    static ref CURRENT_MEMORY_END: UPSafeCell<usize> = unsafe { UPSafeCell::new(MEMORY_END) };
+}
+
+extern "C" {
+    fn ekernel();
+}
+
+/// Set up the frame allocator to manage `[ekernel, MEMORY_END)`.
+pub fn init_frame_allocator() {
+    init_frame_allocator_to(MEMORY_END);
+}
+
+/// Same as [`init_frame_allocator`] but with an explicit end of physical
+/// memory, used when the real size is discovered at runtime (e.g. from the
+/// device tree) rather than hard-coded in [`crate::config::MEMORY_END`].
+pub fn init_frame_allocator_to(memory_end: usize) {
+    FRAME_ALLOCATOR.exclusive_access().init(
+        // `ekernel`'s address comes from the linker script, nowhere near
+        // `usize::MAX`, so this can't actually overflow.
+        PhysAddr::from(ekernel as usize).ceil().unwrap(),
+        PhysAddr::from(memory_end).floor(),
+    );
+    *CURRENT_MEMORY_END.exclusive_access() = memory_end;
+}
+
+/// The physical memory end currently in effect.
+pub fn memory_end() -> usize {
+    *CURRENT_MEMORY_END.exclusive_access()
+}
+
+/// Allocate one physical frame, or `None` once the allocator is exhausted.
+///
+/// A `None` here is exactly where a real swap implementation would step
+/// in: pick a least-recently-used clean/anonymous user page, write it out
+/// to a swap area, unmap it and record the swap slot in its now-invalid
+/// PTE's reserved-for-software bits 8-9, above the eight `PTEFlags` bits
+/// (see `PageTableEntry`), then retry the allocation. None of
+/// that machinery exists in this tree yet: there's no block device driver
+/// under `crate::drivers` to write a swap area to, no working-set/LRU
+/// tracking per `MemorySet` to pick an eviction candidate from, and no
+/// swap-slot-to-PTE encoding decided to read back on the fault that would
+/// bring the page in (the read-back fault itself would plug into
+/// `MemorySet::handle_page_fault` as a new `FaultOutcome`, alongside
+/// `Copied`/`Filled`). Every one of those is its own subsystem-sized piece,
+/// so exhaustion here still just means "out of memory" — callers already
+/// treat a `None`/`KernelError::OutOfMemory` as fatal for the allocation
+/// that asked for it (see e.g. `MemorySet::resolve_cow`), which remains
+/// the correct behavior until eviction actually exists to try first.
+pub fn frame_alloc() -> Option<FrameTracker> {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc()
+        .map(FrameTracker::new)
+}
+
+pub fn frame_dealloc(ppn: PhysPageNum) {
+    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+}
+
+/// Point the global allocator at a fresh `[start, end)` range for a test,
+/// replacing it outright rather than calling `init` on the existing one:
+/// `init` only overwrites `current`/`end`, leaving behind any `recycled`
+/// entries from whatever range an earlier test pointed this same global
+/// allocator at, which would otherwise get handed back out as if they
+/// belonged to the new range.
+#[cfg(test)]
+pub(crate) fn init_frame_allocator_for_test(start: PhysPageNum, end: PhysPageNum) {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    *allocator = FrameAllocatorImpl::new();
+    allocator.init(start, end);
+}
+
+/// Like [`init_frame_allocator_for_test`], but owns picking the backing
+/// range too: allocates a fresh, page-aligned host buffer of `pages` pages
+/// and points the allocator at it. Shared by every `mm` submodule's tests
+/// that need real frames (`memory_set`, `elf`) rather than each rolling its
+/// own copy of this.
+#[cfg(test)]
+pub(crate) fn init_test_frame_allocator(pages: usize) {
+    let layout = std::alloc::Layout::from_size_align(pages * PAGE_SIZE, PAGE_SIZE).unwrap();
+    let ptr = unsafe { std::alloc::alloc(layout) };
+    assert!(!ptr.is_null(), "test harness: failed to allocate backing pages");
+    let start = PhysPageNum(ptr as usize / PAGE_SIZE);
+    init_frame_allocator_for_test(start, PhysPageNum(start.0 + pages));
+}
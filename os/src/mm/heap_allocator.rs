@@ -0,0 +1,21 @@
+//! Kernel heap backing `alloc::*` collections, backed by a static byte
+//! array and a buddy/linked-list allocator.
+
+use crate::config::KERNEL_HEAP_SIZE;
+use buddy_system_allocator::LockedHeap;
+
+// Under `cfg(test)` `std` is linked (see `main.rs`'s doc comment) and
+// brings its own global allocator; registering this one too would conflict
+// with it, so `init_heap` below just becomes unused dead code in that case.
+#[cfg_attr(not(test), global_allocator)]
+static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
+
+static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
+
+pub fn init_heap() {
+    unsafe {
+        HEAP_ALLOCATOR
+            .lock()
+            .init(HEAP_SPACE.as_ptr() as usize, KERNEL_HEAP_SIZE);
+    }
+}
@@ -0,0 +1,1341 @@
+//! Address spaces: [`MapArea`] describes one contiguous mapped region and
+//! [`MemorySet`] owns the page table plus the areas that back it.
+
+use super::address::{
+    vpn_ranges_overlap, PhysAddr, PhysPageNum, StepByOne, VPNRange, VirtAddr, VirtPageNum,
+};
+use super::frame_allocator::{frame_alloc, FrameTracker};
+use super::page_table::{translated_byte_buffer, PTEFlags, PageTable, PageTableEntry};
+use crate::config::{MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// A single frame, zeroed once at allocation and never written to
+    /// directly, shared read-only by every freshly mapped anonymous page
+    /// until something actually writes to it. See
+    /// `MemorySet::insert_mmap_area` (where a page starts out backed by
+    /// this) and `MemorySet::resolve_cow_fault` (where a write to one
+    /// splits it off into a private frame, same as a forked CoW page).
+    static ref ZERO_FRAME: Arc<FrameTracker> =
+        Arc::new(frame_alloc().expect("zero frame: frame allocator not yet initialized"));
+}
+
+/// Failures that can surface from otherwise-infallible-looking `MemorySet`
+/// operations. Kept deliberately small — right now the only way one of
+/// these operations can fail is the frame allocator running dry.
+#[derive(Debug)]
+pub enum KernelError {
+    /// The frame allocator has no free frames left. Callers on a user-fault
+    /// path (e.g. `resolve_cow_fault`) should kill the faulting process
+    /// rather than let this propagate into a kernel panic.
+    OutOfMemory,
+}
+
+bitflags! {
+    pub struct MapPermission: u8 {
+        const R = 1 << 1;
+        const W = 1 << 2;
+        const X = 1 << 3;
+        const U = 1 << 4;
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MapType {
+    /// Identity-mapped: vpn == ppn, used for the kernel's own address space.
+    ///
+    /// Nothing constructs a `MapArea`/`MemorySet` with this variant yet —
+    /// there's no `MemorySet::new_kernel()` that maps `.text`/`.rodata`/
+    /// `.data`/`.bss` from linker-script section symbols, and no
+    /// corresponding `satp` write anywhere in this tree to activate such a
+    /// mapping for kernel-mode execution in the first place (the kernel
+    /// currently runs unpaged; `MemorySet`/`PageTable` only ever back a
+    /// *user* address space, looked up in software via `translated_*` for
+    /// copying to/from user pointers). This variant exists as the intended
+    /// home for that mapping once it's built. Whenever it is, per-section
+    /// permissions can't collide across a page boundary the way a naive
+    /// "compute byte ranges, pick permissions per range" approach risks:
+    /// `MapArea::new` already floors the start and ceils the end to whole
+    /// pages (see its `vpn_range` construction below), so each area's
+    /// first/last page is never silently shared with a neighboring
+    /// section's different permissions unless the linker itself placed two
+    /// sections needing different permissions inside the same page — which
+    /// a linker script should avoid with explicit `ALIGN(4096)` directives
+    /// between sections, not something `MapArea` can detect after the fact.
+    Identical,
+    /// Backed by individually allocated frames.
+    Framed,
+    /// A fixed offset from virtual to physical page number, used to map a
+    /// device's MMIO registers into user space uncached. `0` is stored in
+    /// the variant when the area hasn't computed its base offset yet;
+    /// `MapArea::new_device` fills it in from the requested physical base.
+    Device(usize),
+}
+
+/// One contiguous range of virtual pages mapped the same way.
+pub struct MapArea {
+    pub vpn_range: VPNRange,
+    pub data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
+    pub map_type: MapType,
+    pub map_perm: MapPermission,
+    /// `MAP_SHARED` vs `MAP_PRIVATE`: shared areas map the same frames into
+    /// a forked child, private areas are copy-on-write.
+    pub shared: bool,
+    /// Pages pending copy-on-write. Kept out-of-band here instead of
+    /// stealing one of the PTE's reserved-for-software bits, since a
+    /// write-protected PTE is ambiguous on its own (a genuinely read-only
+    /// private mapping looks identical) and any RSW bit we picked could
+    /// collide with a later use (e.g. accessed/dirty tracking).
+    pub cow_pages: BTreeSet<VirtPageNum>,
+    /// Pages pinned resident by `sys_mlock`. There's no swap-out path in
+    /// this tree to pin against (see
+    /// `MemorySet::mlock`'s doc comment), so this is plumbing only for now:
+    /// a flag a future eviction path would need to consult before reclaiming
+    /// a page.
+    pub locked_pages: BTreeSet<VirtPageNum>,
+    /// Pages marked lazily-freeable by `sys_madvise(MADV_FREE)`: the app
+    /// said it doesn't need the current contents, but the page stays
+    /// mapped with its old data readable until something actually
+    /// reclaims it. See [`MemorySet::reclaim_freeable`].
+    pub freeable_pages: BTreeSet<VirtPageNum>,
+    /// `MAP_GROWSDOWN`: the lowest vpn this area is allowed to extend down
+    /// to on a fault one page below its current `vpn_range.get_start()`,
+    /// or `None` for an ordinary fixed-size area. See
+    /// [`MemorySet::handle_page_fault`]'s growth check.
+    pub growsdown_limit: Option<VirtPageNum>,
+}
+
+impl MapArea {
+    pub fn new(
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        map_type: MapType,
+        map_perm: MapPermission,
+    ) -> Self {
+        let start_vpn = start_va.floor();
+        // Every caller already validated `end_va` (or derived it from an
+        // already-validated `VirtPageNum`) before reaching here — see
+        // `MemorySet::insert_area`/`insert_mmap_area` and `VirtAddr::ceil`'s
+        // doc comment for where the real overflow guard lives.
+        let end_vpn = end_va.ceil().expect("MapArea::new: end_va already validated by caller");
+        Self {
+            vpn_range: VPNRange::new(start_vpn, end_vpn),
+            data_frames: BTreeMap::new(),
+            map_type,
+            map_perm,
+            shared: false,
+            cow_pages: BTreeSet::new(),
+            locked_pages: BTreeSet::new(),
+            freeable_pages: BTreeSet::new(),
+            growsdown_limit: None,
+        }
+    }
+
+    pub fn from_another(another: &MapArea) -> Self {
+        Self {
+            vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
+            data_frames: BTreeMap::new(),
+            map_type: another.map_type,
+            map_perm: another.map_perm,
+            shared: another.shared,
+            cow_pages: BTreeSet::new(),
+            locked_pages: BTreeSet::new(),
+            freeable_pages: BTreeSet::new(),
+            growsdown_limit: another.growsdown_limit,
+        }
+    }
+
+    /// Create a mapping for a device's MMIO registers at `start_va`,
+    /// uncached and with a fixed vpn->ppn offset rather than per-page
+    /// frames, so the user page fault handler never tries to CoW or evict
+    /// it.
+    pub fn new_device(start_va: VirtAddr, end_va: VirtAddr, pa_start: PhysAddr, map_perm: MapPermission) -> Self {
+        let start_vpn = start_va.floor();
+        let end_vpn = end_va
+            .ceil()
+            .expect("MapArea::new_device: end_va already validated by caller");
+        let offset = pa_start.floor().0.wrapping_sub(start_vpn.0);
+        Self {
+            vpn_range: VPNRange::new(start_vpn, end_vpn),
+            data_frames: BTreeMap::new(),
+            map_type: MapType::Device(offset),
+            map_perm,
+            shared: false,
+            cow_pages: BTreeSet::new(),
+            locked_pages: BTreeSet::new(),
+            freeable_pages: BTreeSet::new(),
+            growsdown_limit: None,
+        }
+    }
+
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        // Catch a double-`map` before allocating a frame for it, not after:
+        // `PageTable::map` itself asserts the PTE wasn't already valid, but
+        // by then a fresh frame has already been allocated and handed to
+        // `data_frames`, overwriting (and leaking) whichever frame the
+        // first mapping put there. Checking here means a debug build
+        // panics without ever taking the leaked frame out of the allocator.
+        debug_assert!(
+            !page_table
+                .translate(vpn)
+                .is_some_and(|pte| pte.is_valid()),
+            "map_one: vpn {:?} is already mapped, would leak the old frame",
+            vpn
+        );
+        let ppn: PhysPageNum;
+        match self.map_type {
+            MapType::Identical => {
+                ppn = PhysPageNum(vpn.0);
+            }
+            MapType::Framed => {
+                let frame = frame_alloc().unwrap();
+                ppn = frame.ppn;
+                self.data_frames.insert(vpn, Arc::new(frame));
+            }
+            MapType::Device(offset) => {
+                ppn = PhysPageNum(vpn.0.wrapping_add(offset));
+            }
+        }
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
+        page_table.map(vpn, ppn, pte_flags);
+    }
+
+    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        // Invalidate the PTE *before* dropping the tracked frame. Dropping
+        // `data_frames`' `Arc<FrameTracker>` first can free the frame (when
+        // this was the last reference) while the PTE is still valid — a
+        // window where the allocator could hand the same physical page to
+        // someone else while this address space can still read/write it
+        // through the stale mapping.
+        page_table.unmap(vpn);
+        if self.map_type == MapType::Framed {
+            self.data_frames.remove(&vpn);
+        }
+    }
+
+    pub fn map(&mut self, page_table: &mut PageTable) {
+        for vpn in self.vpn_range {
+            self.map_one(page_table, vpn);
+        }
+    }
+
+    pub fn unmap(&mut self, page_table: &mut PageTable) {
+        for vpn in self.vpn_range {
+            self.unmap_one(page_table, vpn);
+        }
+    }
+
+    /// Copy `data` into the area's frames, starting at the area's first
+    /// page. Assumes `data.len() <= vpn_range` worth of bytes. Fails with
+    /// `KernelError::OutOfMemory` instead of panicking if a page in range
+    /// isn't actually mapped (the area's own `map` having come up short on
+    /// frames) — e.g. during ELF loading, where a panic here would take
+    /// down the whole kernel over one user binary failing to load.
+    pub fn copy_data(
+        &mut self,
+        page_table: &mut PageTable,
+        data: &[u8],
+    ) -> Result<(), KernelError> {
+        assert_eq!(self.map_type, MapType::Framed);
+        let mut start: usize = 0;
+        let mut current_vpn = self.vpn_range.get_start();
+        let len = data.len();
+        loop {
+            let src = &data[start..len.min(start + PAGE_SIZE)];
+            let ppn = page_table
+                .translate(current_vpn)
+                .ok_or(KernelError::OutOfMemory)?
+                .ppn();
+            let dst = &mut ppn.get_bytes_array()[..src.len()];
+            dst.copy_from_slice(src);
+            start += PAGE_SIZE;
+            if start >= len {
+                break;
+            }
+            current_vpn.step();
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::copy_data`], but maps each page on demand instead of
+    /// assuming `Self::map` already mapped the whole range up front: any
+    /// VPN in range with no valid PTE yet is mapped via [`Self::map_one`]
+    /// right before this writes into it. `copy_data`'s `KernelError`
+    /// return exists for its ELF-loading caller, where a page genuinely
+    /// missing from an already-`map`'d area is a real failure to report
+    /// rather than paper over; this variant's whole point is that a
+    /// missing page isn't a failure here; it gets mapped instead — so
+    /// there's nothing left to fail on this path (`map_one` already
+    /// panics on an exhausted allocator, the same as every other caller
+    /// of it), and this returns `()` rather than a `Result` nothing would
+    /// ever populate with `Err`.
+    pub fn copy_data_lazy(&mut self, page_table: &mut PageTable, data: &[u8]) {
+        assert_eq!(self.map_type, MapType::Framed);
+        let mut start: usize = 0;
+        let mut current_vpn = self.vpn_range.get_start();
+        let len = data.len();
+        loop {
+            let src = &data[start..len.min(start + PAGE_SIZE)];
+            if !page_table
+                .translate(current_vpn)
+                .is_some_and(|pte| pte.is_valid())
+            {
+                self.map_one(page_table, current_vpn);
+            }
+            let ppn = page_table.translate(current_vpn).unwrap().ppn();
+            let dst = &mut ppn.get_bytes_array()[..src.len()];
+            dst.copy_from_slice(src);
+            start += PAGE_SIZE;
+            if start >= len {
+                break;
+            }
+            current_vpn.step();
+        }
+    }
+}
+
+/// What kind of access trapped. A real trap handler would decode this from
+/// `scause`/`stval`; nothing in this tree does that yet (see
+/// `MapType::Identical`'s doc comment for the broader "no trap handler
+/// wired up" gap this shares), so today's only caller of
+/// [`MemorySet::handle_page_fault`] is whatever test or future trap entry
+/// point already knows which kind of access it's replaying.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FaultKind {
+    /// A store faulted — the only kind that can mean "this is a CoW or
+    /// lazy-zero page, give the writer its own private copy".
+    Write,
+    /// A load or instruction fetch faulted. Never resolvable by this kernel
+    /// today: every non-CoW, non-lazy page is already mapped with read
+    /// permission the moment its area is pushed, so a read fault here
+    /// always means a genuinely unmapped access.
+    Read,
+}
+
+/// What [`MemorySet::handle_page_fault`] decided to do about a fault, for a
+/// trap handler to act on without knowing any of the area-classification
+/// logic that produced it — the single dispatch point this replaces the
+/// CoW-only `resolve_cow_fault` call plus whatever parallel lazy-fill
+/// `if`/`else` a future demand-paging path would otherwise have bolted on
+/// next to it.
+pub enum FaultOutcome {
+    /// A CoW page was split off into its own private frame.
+    Copied(PhysPageNum),
+    /// A lazily-backed page (today: the shared `ZERO_FRAME` behind a fresh
+    /// anonymous `mmap`, see `MemorySet::insert_mmap_area`) was given its
+    /// own private frame.
+    Filled(PhysPageNum),
+    /// A `MAP_GROWSDOWN` area was extended one page further down to cover
+    /// the faulting page. Nothing marks a `brk`-managed heap area growable
+    /// the same way yet — only `sys_mmap(MAP_GROWSDOWN)` areas reach this
+    /// today — but the outcome stays named for "growable area" generally
+    /// rather than specifically for a stack, since heap growth would
+    /// resolve the same way once a distinct heap area exists to mark.
+    Grew,
+    /// Not recoverable: `vpn` isn't covered by any area, the access kind
+    /// doesn't match what a CoW/lazy page allows (e.g. a load fault, which
+    /// this kernel's eager mapping means can only be a genuinely unmapped
+    /// address), or the frame allocator is out of memory. The caller
+    /// should deliver `SIGSEGV` (or kill the process outright, absent a
+    /// trap handler wired up to check `signal_actions`) rather than
+    /// resume.
+    Fatal,
+}
+
+/// An address space: a page table plus the list of areas mapped into it.
+pub struct MemorySet {
+    pub page_table: PageTable,
+    pub areas: Vec<MapArea>,
+    /// Index into `areas` most recently found to cover a vpn. CoW faults
+    /// and other translate-heavy paths tend to touch the same area
+    /// repeatedly in a row (e.g. a tight write loop), so checking this hint
+    /// before the general linear scan turns the common case into O(1)
+    /// instead of O(areas.len()) every single fault.
+    last_area_hint: usize,
+    /// Highest `current_rss_pages()` has ever been for this address space.
+    /// Updated by the handful of call sites that can grow RSS (mapping a
+    /// new framed area, a CoW fault allocating a fresh frame); never
+    /// decremented on unmap, since it's a watermark, not a live count.
+    peak_rss_pages: usize,
+    /// Number of lazy/CoW page faults this address space has actually taken,
+    /// i.e. calls to [`Self::resolve_cow_fault`]. A page eagerly backed up
+    /// front (a `Framed`/`Identical` area, or a page [`Self::prefault`]
+    /// resolves ahead of time) never increments this — it only counts
+    /// faults that were genuinely deferred and then had to be paid for.
+    minor_faults: usize,
+}
+
+impl MemorySet {
+    pub fn new_bare() -> Self {
+        Self {
+            page_table: PageTable::new(),
+            areas: Vec::new(),
+            last_area_hint: 0,
+            peak_rss_pages: 0,
+            minor_faults: 0,
+        }
+    }
+
+    /// Number of frames currently backing this address space — `Framed`
+    /// areas only, since `Identical`/`Device` pages aren't allocator-owned
+    /// and don't count against the process's resident set.
+    pub fn current_rss_pages(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|a| a.map_type == MapType::Framed)
+            .map(|a| a.data_frames.len())
+            .sum()
+    }
+
+    pub fn peak_rss_pages(&self) -> usize {
+        self.peak_rss_pages
+    }
+
+    /// Total lazy/CoW faults resolved against this address space so far.
+    /// See the field doc on [`Self::minor_faults`] for what does and
+    /// doesn't count.
+    pub fn minor_faults(&self) -> usize {
+        self.minor_faults
+    }
+
+    /// Recompute `current_rss_pages()` and bump the watermark if it grew.
+    /// Called after any operation that can add frames to the address space.
+    pub(crate) fn note_rss_growth(&mut self) {
+        let current = self.current_rss_pages();
+        if current > self.peak_rss_pages {
+            self.peak_rss_pages = current;
+        }
+    }
+
+    /// Find the index of the area covering `vpn`, checking the
+    /// most-recently-used area first. See `last_area_hint`.
+    fn area_idx_containing(&mut self, vpn: VirtPageNum) -> Option<usize> {
+        let covers = |a: &MapArea| a.vpn_range.get_start().0 <= vpn.0 && vpn.0 < a.vpn_range.get_end().0;
+        if self.areas.get(self.last_area_hint).is_some_and(covers) {
+            return Some(self.last_area_hint);
+        }
+        let idx = self.areas.iter().position(covers)?;
+        self.last_area_hint = idx;
+        Some(idx)
+    }
+
+    pub fn token(&self) -> usize {
+        self.page_table.token()
+    }
+
+    /// `va -> floor() -> translate -> ppn -> + page offset` in one call,
+    /// instead of each caller that needs a physical address for a virtual
+    /// one repeating those steps itself. Returns `None` if `va`'s page
+    /// isn't mapped.
+    pub fn translate_addr(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.page_table.translate_va(va)
+    }
+
+    /// Same user/kernel pointer crossing as the free function
+    /// [`translated_byte_buffer`], but as a method on the address space
+    /// that already has the `PageTable` it needs, instead of a caller
+    /// round-tripping through [`Self::token`] and `PageTable::from_token`
+    /// just to get back to this same table.
+    pub fn translate_bytes(&self, va: VirtAddr, len: usize) -> Vec<&'static mut [u8]> {
+        translated_byte_buffer(self.token(), va.0 as *const u8, len)
+    }
+
+    /// Checks that every page in `[va, va + len)` is covered by an area
+    /// granting both `need` and `MapPermission::U`. `PageTable::translate`
+    /// only reports whatever permission bits happen to already be burned
+    /// into a live PTE — a COW page, for instance, is read-only at the PTE
+    /// level right up until the write fault that makes it writable — so
+    /// it can't answer "is this address *supposed* to be writable", only
+    /// "is it writable right now". This walks the area list itself instead,
+    /// and requires every area touched by the range to grant `need`, so a
+    /// range spanning two areas is only `access_ok` if both do.
+    pub fn access_ok(&mut self, va: VirtAddr, len: usize, need: MapPermission) -> bool {
+        if len == 0 {
+            return true;
+        }
+        let mut vpn = va.floor();
+        let end_vpn = match VirtAddr::from(va.0 + len).ceil() {
+            Some(vpn) => vpn,
+            None => return false,
+        };
+        while vpn.0 < end_vpn.0 {
+            let Some(idx) = self.area_idx_containing(vpn) else {
+                return false;
+            };
+            let perm = self.areas[idx].map_perm;
+            if !perm.contains(need) || !perm.contains(MapPermission::U) {
+                return false;
+            }
+            vpn.step();
+        }
+        true
+    }
+
+    /// Copy `dst.len()` bytes from user address `va` into a kernel-owned
+    /// buffer, after checking [`Self::access_ok`] for `MapPermission::R`
+    /// over the whole range. Returns `false` (leaving `dst` untouched) if
+    /// the check fails, instead of translating whatever happens to be
+    /// mapped there the way [`Self::translate_bytes`] does.
+    pub fn copy_from_user(&mut self, va: VirtAddr, dst: &mut [u8]) -> bool {
+        if !self.access_ok(va, dst.len(), MapPermission::R) {
+            return false;
+        }
+        let mut offset = 0;
+        for slice in self.translate_bytes(va, dst.len()) {
+            dst[offset..offset + slice.len()].copy_from_slice(slice);
+            offset += slice.len();
+        }
+        true
+    }
+
+    /// The write-side mirror of [`Self::copy_from_user`]: copies `src` into
+    /// user address `va` after checking [`Self::access_ok`] for
+    /// `MapPermission::W`.
+    pub fn copy_to_user(&mut self, va: VirtAddr, src: &[u8]) -> bool {
+        if !self.access_ok(va, src.len(), MapPermission::W) {
+            return false;
+        }
+        let mut offset = 0;
+        for slice in self.translate_bytes(va, src.len()) {
+            slice.copy_from_slice(&src[offset..offset + slice.len()]);
+            offset += slice.len();
+        }
+        true
+    }
+
+    /// Total virtual pages spanned by every area, for tests asserting on
+    /// the shape of the address space after a sequence of map/unmap calls
+    /// without needing `areas` itself to be public.
+    pub fn mapped_page_count(&self) -> usize {
+        self.areas
+            .iter()
+            .map(|a| a.vpn_range.get_end().0 - a.vpn_range.get_start().0)
+            .sum()
+    }
+
+    /// A read-only `(start, end, permission)` view of every area, in the
+    /// same order as `areas`, for the same test-assertion purpose as
+    /// `mapped_page_count`.
+    pub fn areas_snapshot(&self) -> Vec<(VirtPageNum, VirtPageNum, MapPermission)> {
+        self.areas
+            .iter()
+            .map(|a| (a.vpn_range.get_start(), a.vpn_range.get_end(), a.map_perm))
+            .collect()
+    }
+
+    /// Map an area backed by frames, optionally initializing it from `data`.
+    pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> Result<(), KernelError> {
+        map_area.map(&mut self.page_table);
+        let result = match data {
+            Some(data) => map_area.copy_data(&mut self.page_table, data),
+            None => Ok(()),
+        };
+        self.areas.push(map_area);
+        self.note_rss_growth();
+        result
+    }
+
+    /// Map an area backed by frames without copying any initial data, used
+    /// for anonymous mmap regions that must start zeroed.
+    pub fn push_mmap(&mut self, mut map_area: MapArea) {
+        map_area.map(&mut self.page_table);
+        self.areas.push(map_area);
+        self.note_rss_growth();
+    }
+
+    /// Map a framed area, panicking if it overlaps an existing one.
+    /// `data`, when given, is copied in; otherwise the area is left zeroed,
+    /// which is what `push`/`push_mmap` used to differ on. Fails with
+    /// `KernelError::OutOfMemory` if copying `data` in hits an unmapped
+    /// page — see `MapArea::copy_data`.
+    pub fn insert_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+        data: Option<&[u8]>,
+    ) -> Result<(), KernelError> {
+        let start_vpn = start_va.floor();
+        // Callers reachable from a syscall (e.g. `sys_mmap`) already ran
+        // `length` through a fallible `ceil()` of their own before ever
+        // computing `end_va` — see `VirtAddr::ceil`'s doc comment.
+        let end_vpn = end_va.ceil().expect("insert_area: end_va already validated by caller");
+        assert!(
+            !self.areas.iter().any(|a| {
+                vpn_ranges_overlap(start_vpn, end_vpn, a.vpn_range.get_start(), a.vpn_range.get_end())
+            }),
+            "insert_area: [{:?}, {:?}) overlaps an existing area",
+            start_vpn,
+            end_vpn
+        );
+        self.push(MapArea::new(start_va, end_va, MapType::Framed, permission), data)
+    }
+
+    /// Thin wrapper kept for source compatibility with callers that never
+    /// had initial data to copy in — with no data to copy, `insert_area`
+    /// can't actually fail, so there's nothing for this wrapper to
+    /// propagate.
+    pub fn insert_framed_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        self.insert_area(start_va, end_va, permission, None)
+            .expect("insert_framed_area: no data to copy, can't fail");
+    }
+
+    /// A fresh anonymous mapping is all zeros, so every page starts out
+    /// backed by the single shared [`ZERO_FRAME`] rather than its own
+    /// freshly allocated (and separately zeroed) frame, read-only, with a
+    /// pending-CoW marker if the mapping is writable at all — the exact
+    /// same sharing scheme `from_copy_on_write` uses for a forked private
+    /// area, just with every area's first "sibling" being the zero frame
+    /// instead of another address space. The first write to a page splits
+    /// it off into a real private frame via `resolve_cow_fault`; a
+    /// never-written page never costs more than the one shared frame no
+    /// matter how many pages or mappings point at it. `shared` starts
+    /// `false`, same as every other private area — `sys_mmap` only calls
+    /// this for `MAP_PRIVATE`; an anonymous `MAP_SHARED` mapping is built
+    /// with real frames via `insert_area` like any other shared area (the
+    /// zero-frame trick only applies to `MAP_PRIVATE`'s read-before-write
+    /// window).
+    pub fn insert_mmap_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        let start_vpn = start_va.floor();
+        // Same as `insert_area`: the caller already validated `end_va`.
+        let end_vpn = end_va.ceil().expect("insert_mmap_area: end_va already validated by caller");
+        let mut area = MapArea::new(start_va, end_va, MapType::Framed, permission);
+        let ro_flags = PTEFlags::from_bits(permission.bits()).unwrap() - PTEFlags::W;
+        let writable = permission.contains(MapPermission::W);
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            self.page_table.map(vpn, ZERO_FRAME.ppn, ro_flags);
+            area.data_frames.insert(vpn, ZERO_FRAME.clone());
+            if writable {
+                area.cow_pages.insert(vpn);
+            }
+        }
+        self.areas.push(area);
+        self.note_rss_growth();
+        // The mapping's first page is the one page in a fresh `mmap` call
+        // the caller is all but certain to touch right away (it's exactly
+        // the address `mmap` just handed back) — prefaulting it here turns
+        // that guaranteed first write into a cheap eager frame instead of a
+        // lazy fault. Every later page is left on the zero frame, same as
+        // before: unlike the entry page, nothing says they'll ever be
+        // touched. Best-effort — an exhausted allocator just leaves the
+        // page lazy, same as if this call weren't here at all.
+        if writable {
+            let _ = self.prefault(start_vpn);
+        }
+    }
+
+    /// Mark the area starting at `start_vpn` (as created by
+    /// [`Self::insert_mmap_area`]) as `MAP_GROWSDOWN`: a fault one page
+    /// below its current bottom extends it there instead of being fatal,
+    /// as long as the new bottom would still be at or above `limit_vpn` —
+    /// see `handle_page_fault`'s growth check. Returns `false` if no area
+    /// starts at `start_vpn`.
+    pub fn mark_growsdown(&mut self, start_vpn: VirtPageNum, limit_vpn: VirtPageNum) -> bool {
+        match self
+            .areas
+            .iter_mut()
+            .find(|a| a.vpn_range.get_start() == start_vpn)
+        {
+            Some(area) => {
+                area.growsdown_limit = Some(limit_vpn);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The `MAP_GROWSDOWN` half of [`Self::handle_page_fault`]: a fault on
+    /// `vpn` that isn't covered by any area is still recoverable if `vpn`
+    /// is exactly one page below a growsdown area's current bottom and
+    /// still at or above that area's `growsdown_limit` — "auto-extend on
+    /// underflow, up to a cap" for an `mmap(MAP_GROWSDOWN)` region. The main
+    /// user stack `from_elf` sets up has no `growsdown_limit` and never
+    /// grows this way. The new page is a
+    /// freshly zeroed, eagerly allocated private frame rather than the
+    /// shared `ZERO_FRAME`/CoW path `insert_mmap_area` otherwise uses for a
+    /// lazy page — a stack-growth page is about to be written (it's a push
+    /// growing downward) and is never shared with another address space,
+    /// so there's nothing to defer.
+    fn try_grow_down(&mut self, vpn: VirtPageNum) -> FaultOutcome {
+        let area_idx = match self.areas.iter().position(|a| {
+            a.growsdown_limit
+                .is_some_and(|limit| vpn.0 + 1 == a.vpn_range.get_start().0 && limit.0 <= vpn.0)
+        }) {
+            Some(idx) => idx,
+            None => return FaultOutcome::Fatal,
+        };
+        let frame = match frame_alloc() {
+            Some(f) => f,
+            None => return FaultOutcome::Fatal,
+        };
+        let ppn = frame.ppn;
+        ppn.get_bytes_array().fill(0);
+        let area = &mut self.areas[area_idx];
+        let flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap() | PTEFlags::V;
+        self.page_table.map(vpn, ppn, flags);
+        let area = &mut self.areas[area_idx];
+        area.data_frames.insert(vpn, Arc::new(frame));
+        area.vpn_range = VPNRange::new(vpn, area.vpn_range.get_end());
+        self.note_rss_growth();
+        FaultOutcome::Grew
+    }
+
+    /// Map a single physical page at `pa` into this address space at `va`,
+    /// uncached and with a fixed offset like a device region (see
+    /// [`MapType::Device`]) rather than a tracked, allocator-owned frame —
+    /// the safe counterpart to reaching for raw `page_table.map` calls when
+    /// all a caller needs is "put this one physical page somewhere in user
+    /// space" (e.g. handing a single MMIO register page to a driver, or a
+    /// one-off shared page). Panics if `va`'s page overlaps an existing
+    /// area, same as [`Self::insert_area`].
+    pub fn map_physical_page(&mut self, va: VirtAddr, pa: PhysAddr, perm: MapPermission) {
+        let start_vpn = va.floor();
+        let end_vpn = VirtPageNum(start_vpn.0 + 1);
+        assert!(
+            !self.areas.iter().any(|a| {
+                vpn_ranges_overlap(start_vpn, end_vpn, a.vpn_range.get_start(), a.vpn_range.get_end())
+            }),
+            "map_physical_page: {:?} overlaps an existing area",
+            start_vpn
+        );
+        let mut area = MapArea::new_device(start_vpn.into(), end_vpn.into(), pa, perm);
+        area.map(&mut self.page_table);
+        self.areas.push(area);
+    }
+
+    /// Change the mapped permission of an existing area, identified by its
+    /// start vpn (same lookup convention as `remove_area_with_start_vpn`),
+    /// updating every already-mapped PTE in place. Re-adding a bit (e.g.
+    /// `X` after a round-trip that dropped it) takes effect immediately on
+    /// the live mapping instead of waiting for a future fault to re-derive
+    /// it from `map_perm`. Returns `false` if no area starts at `start_vpn`.
+    pub fn set_area_permission(&mut self, start_vpn: VirtPageNum, new_perm: MapPermission) -> bool {
+        let area_idx = match self
+            .areas
+            .iter()
+            .position(|a| a.vpn_range.get_start() == start_vpn)
+        {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let area = &mut self.areas[area_idx];
+        area.map_perm = new_perm;
+        let flags = PTEFlags::from_bits(new_perm.bits()).unwrap() | PTEFlags::V;
+        for vpn in area.vpn_range {
+            if let Some(pte) = self.page_table.find_pte(vpn) {
+                if pte.is_valid() {
+                    pte.set_flags(flags);
+                }
+            }
+        }
+        true
+    }
+
+    /// Pin every page in `[start_vpn, end_vpn)` resident, the `sys_mlock`
+    /// semantics. Every page in every area in this tree has a valid PTE from
+    /// creation on (an `insert_mmap_area` page points at the shared
+    /// `ZERO_FRAME` until its first write), and there's no swap-out path for
+    /// a lock to actually defend a page against (see
+    /// `MapArea::locked_pages`'s doc comment) — so today this only records
+    /// which pages are locked, for a future eviction path to consult; it does
+    /// not give a zero-frame page a private frame of its own. It also
+    /// validates the one thing `mlock(2)` genuinely needs to fail without:
+    /// returns `false` (the caller's `-ENOMEM`) if any page in the range
+    /// isn't currently mapped, without marking anything locked.
+    pub fn mlock(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        let mut vpn = start_vpn;
+        while vpn.0 < end_vpn.0 {
+            if !self
+                .page_table
+                .translate(vpn)
+                .is_some_and(|pte| pte.is_valid())
+            {
+                return false;
+            }
+            vpn.step();
+        }
+        let mut vpn = start_vpn;
+        while vpn.0 < end_vpn.0 {
+            if let Some(idx) = self.area_idx_containing(vpn) {
+                self.areas[idx].locked_pages.insert(vpn);
+            }
+            vpn.step();
+        }
+        true
+    }
+
+    /// Undo [`MemorySet::mlock`] over `[start_vpn, end_vpn)`. Unlike
+    /// `mlock`, unlocking a never-locked or already-unmapped page isn't an
+    /// error — `munlock(2)` doesn't fail on that either.
+    pub fn munlock(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let mut vpn = start_vpn;
+        while vpn.0 < end_vpn.0 {
+            if let Some(idx) = self.area_idx_containing(vpn) {
+                self.areas[idx].locked_pages.remove(&vpn);
+            }
+            vpn.step();
+        }
+    }
+
+    /// Mark every page in `[start_vpn, end_vpn)` lazily-freeable, the
+    /// `sys_madvise(MADV_FREE)` semantics: unlike `MADV_DONTNEED`, nothing
+    /// changes about the mapping yet — the page stays mapped, stays
+    /// readable, and keeps its current contents until
+    /// [`Self::reclaim_freeable`] actually reclaims it. A locked page
+    /// (`MapArea::locked_pages`, from `sys_mlock`) is left out, same as a
+    /// real kernel never frees a page the app asked to pin. Silently skips
+    /// any page outside a mapped area, same as `munlock`.
+    pub fn madvise_free(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let mut vpn = start_vpn;
+        while vpn.0 < end_vpn.0 {
+            if let Some(idx) = self.area_idx_containing(vpn) {
+                if !self.areas[idx].locked_pages.contains(&vpn) {
+                    self.areas[idx].freeable_pages.insert(vpn);
+                    // Start from a clean Dirty bit so `reclaim_freeable`
+                    // can tell a page written to after this call apart.
+                    self.clear_dirty(vpn);
+                }
+            }
+            vpn.step();
+        }
+    }
+
+    /// Actually reclaim every page across every area still marked
+    /// freeable by [`Self::madvise_free`] and not written to since: remap
+    /// it onto the shared `ZERO_FRAME`, dropping its private frame (the
+    /// `Arc<FrameTracker>` in `data_frames` is simply replaced, so the old
+    /// frame is freed as soon as nothing else references it) without
+    /// writing anything out, since the app already said it doesn't need
+    /// the contents. The reclaimed page is left in `cow_pages` pointing at
+    /// `ZERO_FRAME` exactly like a fresh `insert_mmap_area` page, so the
+    /// very next write to it goes through the ordinary
+    /// `Self::handle_page_fault` CoW path and transparently gets a new
+    /// private frame — "the next write keeps them" falls out of reusing
+    /// that machinery rather than needing its own.
+    ///
+    /// There's no background scanner anywhere in this tree that calls this
+    /// under real memory pressure (see `frame_alloc`'s doc comment for the
+    /// rest of that gap) — today this only runs when something calls it
+    /// directly, which in turn means a page can sit marked freeable
+    /// indefinitely with its old contents still intact and still readable
+    /// until that happens, same as a real kernel under no memory pressure
+    /// at all would just never get around to it either.
+    pub fn reclaim_freeable(&mut self) {
+        for area in self.areas.iter_mut() {
+            let vpns: Vec<VirtPageNum> = area.freeable_pages.iter().copied().collect();
+            for vpn in vpns {
+                if self.page_table.translate(vpn).is_some_and(|pte| pte.dirty()) {
+                    // Written to since `madvise_free`: the app wants it back.
+                    area.freeable_pages.remove(&vpn);
+                    continue;
+                }
+                let ro_flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap() - PTEFlags::W;
+                self.page_table.unmap(vpn);
+                self.page_table.map(vpn, ZERO_FRAME.ppn, ro_flags);
+                area.data_frames.insert(vpn, ZERO_FRAME.clone());
+                area.cow_pages.insert(vpn);
+                area.freeable_pages.remove(&vpn);
+            }
+        }
+    }
+
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some((idx, area)) = self
+            .areas
+            .iter_mut()
+            .enumerate()
+            .find(|(_, area)| area.vpn_range.get_start() == start_vpn)
+        {
+            area.unmap(&mut self.page_table);
+            self.areas.remove(idx);
+        }
+    }
+
+    /// Build a deep copy of `user_space`, used by `fork` when copy-on-write
+    /// isn't in play. Shared areas map the same frames into the copy so
+    /// writes through either address space are visible to both; private
+    /// areas get their own frames with the data copied in.
+    pub fn from_existed_user(user_space: &MemorySet) -> MemorySet {
+        let mut memory_set = Self::new_bare();
+        memory_set.map_trampoline();
+        for area in user_space.areas.iter() {
+            let mut new_area = MapArea::from_another(area);
+            if area.shared {
+                new_area.vpn_range = area.vpn_range;
+                for (vpn, frame) in area.data_frames.iter() {
+                    let pte_flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap();
+                    memory_set.page_table.map(*vpn, frame.ppn, pte_flags);
+                    new_area.data_frames.insert(*vpn, frame.clone());
+                }
+                memory_set.areas.push(new_area);
+            } else {
+                memory_set
+                    .push(new_area, None)
+                    .expect("from_existed_user: no data to copy, can't fail");
+                for vpn in area.vpn_range {
+                    if let Some(src_ppn) = user_space.page_table.translate(vpn).map(|p| p.ppn()) {
+                        let dst_ppn = memory_set.page_table.translate(vpn).unwrap().ppn();
+                        dst_ppn
+                            .get_bytes_array()
+                            .copy_from_slice(src_ppn.get_bytes_array());
+                    }
+                }
+            }
+        }
+        memory_set
+    }
+
+    /// The default `fork` entry point: delegates to [`Self::from_copy_on_write`]
+    /// so every private area — notably the user stack, which is both large
+    /// and usually the first thing the child writes to on return from
+    /// `fork` — is duplicated lazily instead of `from_existed_user`'s eager
+    /// byte-for-byte copy. Kept as a separate name from
+    /// `from_copy_on_write` so call sites read as "fork this address space"
+    /// rather than naming the underlying mechanism.
+    pub fn fork(user_space: &mut MemorySet) -> MemorySet {
+        Self::from_copy_on_write(user_space)
+    }
+
+    /// Build a copy-on-write copy of `user_space`, used by `fork`. Shared
+    /// areas are mapped identically (writes propagate both ways); private
+    /// areas have both parent and child PTEs stripped of `W` and share the
+    /// same frame until one side writes to it (see `resolve_cow_fault`).
+    pub fn from_copy_on_write(user_space: &mut MemorySet) -> MemorySet {
+        let mut memory_set = Self::new_bare();
+        memory_set.map_trampoline();
+        for area in user_space.areas.iter_mut() {
+            let mut new_area = MapArea::from_another(area);
+            new_area.vpn_range = area.vpn_range;
+            if area.shared {
+                for (vpn, frame) in area.data_frames.iter() {
+                    let pte_flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap();
+                    memory_set.page_table.map(*vpn, frame.ppn, pte_flags);
+                    new_area.data_frames.insert(*vpn, frame.clone());
+                }
+            } else {
+                for (vpn, frame) in area.data_frames.iter() {
+                    let ro_flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap() - PTEFlags::W;
+                    if let Some(pte) = user_space.page_table.find_pte(*vpn) {
+                        pte.set_flags(ro_flags);
+                    }
+                    memory_set.page_table.map(*vpn, frame.ppn, ro_flags);
+                    new_area.data_frames.insert(*vpn, frame.clone());
+                    // Record the pending CoW out-of-band on both sides
+                    // rather than relying on the now-ambiguous missing `W`
+                    // bit: a plain read-only private mapping looks the same
+                    // at the PTE level.
+                    area.cow_pages.insert(*vpn);
+                    new_area.cow_pages.insert(*vpn);
+                }
+            }
+            memory_set.areas.push(new_area);
+        }
+        memory_set.note_rss_growth();
+        memory_set
+    }
+
+    /// How many page tables currently hold a frame mapped at `vpn`, via the
+    /// `Arc<FrameTracker>` shared between every CoW sibling's `data_frames`.
+    /// Used to decide whether a write fault actually needs to copy (> 1)
+    /// or can just flip the page back to writable in place (== 1, the
+    /// fault handler is the sole remaining owner).
+    pub fn frame_ref_count(&mut self, vpn: VirtPageNum) -> usize {
+        self.area_idx_containing(vpn)
+            .and_then(|idx| self.areas[idx].data_frames.get(&vpn))
+            .map(Arc::strong_count)
+            .unwrap_or(0)
+    }
+
+    /// Whether `vpn` is a page pending copy-on-write, per the out-of-band
+    /// marker rather than any PTE bit.
+    pub fn is_cow_page(&mut self, vpn: VirtPageNum) -> bool {
+        self.area_idx_containing(vpn)
+            .is_some_and(|idx| self.areas[idx].cow_pages.contains(&vpn))
+    }
+
+    /// Classify `vpn` against the area that covers it and resolve whatever
+    /// kind of recoverable fault `fault_kind` describes, in one call instead
+    /// of a trap handler's own `if`/`else` chain over CoW vs. lazy-fill vs.
+    /// (eventually) stack/heap growth. See [`FaultOutcome`] for what each
+    /// variant means and [`FaultKind`] for what this needs to know about the
+    /// trapping access.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, fault_kind: FaultKind) -> FaultOutcome {
+        let area_idx = match self.area_idx_containing(vpn) {
+            Some(idx) => idx,
+            None => return self.try_grow_down(vpn),
+        };
+        let former_ppn = match self.page_table.translate(vpn) {
+            Some(pte) => pte.ppn(),
+            None => return FaultOutcome::Fatal,
+        };
+        if fault_kind != FaultKind::Write {
+            return FaultOutcome::Fatal;
+        }
+        let is_cow = self.areas[area_idx].cow_pages.contains(&vpn);
+        let is_lazy_zero = former_ppn == ZERO_FRAME.ppn;
+        if !is_cow && !is_lazy_zero {
+            return FaultOutcome::Fatal;
+        }
+        match self.resolve_cow_fault(vpn, former_ppn) {
+            Ok(ppn) if is_lazy_zero => FaultOutcome::Filled(ppn),
+            Ok(ppn) => FaultOutcome::Copied(ppn),
+            Err(KernelError::OutOfMemory) => FaultOutcome::Fatal,
+        }
+    }
+
+    /// Handle a write fault on a CoW page: allocate a fresh frame, copy the
+    /// shared page's contents into it, and remap `vpn` to the new frame.
+    /// Returns the new frame's PPN, or `KernelError::OutOfMemory` if the
+    /// allocator has nothing left to give — the caller (the user-fault
+    /// handler) should kill the faulting process on that, not panic the
+    /// kernel over a single user page fault. Named for what it resolves
+    /// rather than the generic "alloc", now that it's a plain Rust method
+    /// with no assembly caller to keep a stable symbol name for; there's no
+    /// `#[no_mangle]` on it and there never was anything in `trap/` invoking
+    /// it by mangled name.
+    pub fn resolve_cow_fault(
+        &mut self,
+        vpn: VirtPageNum,
+        former_ppn: PhysPageNum,
+    ) -> Result<PhysPageNum, KernelError> {
+        self.minor_faults += 1;
+        self.resolve_cow(vpn, former_ppn)
+    }
+
+    /// Eagerly resolve a page that would otherwise be left for
+    /// [`Self::resolve_cow_fault`] to pick up on first touch — same
+    /// underlying copy/remap as a real fault, just not counted as one in
+    /// [`Self::minor_faults`], since nothing actually faulted. Used right
+    /// after [`Self::insert_mmap_area`] sets up a mapping's first page: that
+    /// page is overwhelmingly likely to be touched immediately (the caller
+    /// just asked for this memory), so eagerly resolving it trades one
+    /// guaranteed-useful frame for one guaranteed-avoided fault. Returns the
+    /// new frame's PPN, or `KernelError::OutOfMemory` on an exhausted
+    /// allocator — non-fatal for the caller, which can just leave the page
+    /// lazy and let a real fault retry it later.
+    pub fn prefault(&mut self, vpn: VirtPageNum) -> Result<PhysPageNum, KernelError> {
+        let former_ppn = self
+            .page_table
+            .translate(vpn)
+            .expect("prefault: vpn has no mapping to prefault")
+            .ppn();
+        self.resolve_cow(vpn, former_ppn)
+    }
+
+    /// Whether `vpn`'s PTE has its Accessed bit set, or `false` if `vpn`
+    /// isn't mapped. See [`PageTableEntry::accessed`]; this and its
+    /// siblings below are the per-VPN entry point an LRU eviction scan
+    /// (for swap, once that exists) or `msync` uses instead of reaching
+    /// into `page_table` directly.
+    pub fn is_accessed(&self, vpn: VirtPageNum) -> bool {
+        self.page_table
+            .translate(vpn)
+            .is_some_and(|pte| pte.accessed())
+    }
+
+    /// Whether `vpn`'s PTE has its Dirty bit set, or `false` if `vpn`
+    /// isn't mapped. See [`PageTableEntry::dirty`].
+    pub fn is_dirty(&self, vpn: VirtPageNum) -> bool {
+        self.page_table
+            .translate(vpn)
+            .is_some_and(|pte| pte.dirty())
+    }
+
+    /// Clear `vpn`'s Accessed bit. A no-op if `vpn` isn't mapped.
+    pub fn clear_accessed(&mut self, vpn: VirtPageNum) {
+        if let Some(pte) = self.page_table.find_pte(vpn) {
+            pte.clear_accessed();
+        }
+    }
+
+    /// Clear `vpn`'s Dirty bit. A no-op if `vpn` isn't mapped.
+    pub fn clear_dirty(&mut self, vpn: VirtPageNum) {
+        if let Some(pte) = self.page_table.find_pte(vpn) {
+            pte.clear_dirty();
+        }
+    }
+
+    fn resolve_cow(
+        &mut self,
+        vpn: VirtPageNum,
+        former_ppn: PhysPageNum,
+    ) -> Result<PhysPageNum, KernelError> {
+        let ref_count = self.frame_ref_count(vpn);
+        debug_assert!(
+            ref_count >= 1,
+            "resolve_cow_fault: vpn {:?} has no tracked frame to copy from",
+            vpn
+        );
+        // The ref-count-1 shortcut below hands `former_ppn` back to the
+        // faulting address space as its own private, writable frame — correct
+        // for a genuine fork sibling, but `ZERO_FRAME` is shared by every
+        // zero-filled mapping in the system regardless of what any one
+        // area's `Arc` strong count says, so it must always take the
+        // allocate-and-copy path instead, even when this happens to be the
+        // last live reference to it.
+        if ref_count == 1 && former_ppn != ZERO_FRAME.ppn {
+            // No other address space still shares this frame (e.g. a
+            // sibling that held it CoW has since exited) — the fault can be
+            // resolved by just taking back the `W` bit and clearing the
+            // pending-CoW marker, with no new frame or copy needed.
+            let area_idx = self
+                .area_idx_containing(vpn)
+                .expect("resolve_cow_fault: vpn not covered by any area");
+            let area = &mut self.areas[area_idx];
+            let flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap() | PTEFlags::V;
+            let pte = self.page_table.find_pte(vpn).unwrap();
+            *pte = PageTableEntry::new(former_ppn, flags);
+            area.cow_pages.remove(&vpn);
+            return Ok(former_ppn);
+        }
+        let frame = frame_alloc().ok_or(KernelError::OutOfMemory)?;
+        let ppn = frame.ppn;
+        assert_ne!(
+            former_ppn, ppn,
+            "resolve_cow_fault: allocator handed back the page being copied, would copy-to-self"
+        );
+        ppn.get_bytes_array()
+            .copy_from_slice(former_ppn.get_bytes_array());
+        self.remap_cow(vpn, ppn, frame);
+        self.note_rss_growth();
+        Ok(ppn)
+    }
+
+    /// Swap the page table entry for `vpn` from the shared `former_ppn` to
+    /// the freshly-copied `ppn`, taking over ownership of `frame`.
+    ///
+    /// This address space's share of `former_ppn`'s refcount is released
+    /// here too, not just the page table mapping: `data_frames.insert`
+    /// replaces this area's `Arc<FrameTracker>` for `vpn`, and the
+    /// discarded old `Arc` — this area's only reference into the CoW
+    /// sharing group, each sibling address space holding its own separate
+    /// clone — is dropped right along with the `Option` `insert` returns.
+    /// A fork sibling that's already written its own copy (and so already
+    /// dropped its own `Arc`) leaves this as the last reference, and
+    /// `FrameTracker::drop`'s `frame_dealloc` runs as part of dropping it
+    /// here — there's no separate manual refcount to maintain, the `Arc`
+    /// already *is* the refcount.
+    fn remap_cow(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, frame: FrameTracker) {
+        let pte = self.page_table.find_pte(vpn).unwrap();
+        let former_ppn = pte.ppn();
+        assert_ne!(
+            former_ppn, ppn,
+            "remap_cow: new mapping is identical to the old one"
+        );
+        let area_idx = self
+            .area_idx_containing(vpn)
+            .expect("remap_cow: vpn not covered by any area");
+        let area = &mut self.areas[area_idx];
+        let flags = PTEFlags::from_bits(area.map_perm.bits()).unwrap();
+        self.page_table.unmap(vpn);
+        self.page_table.map(vpn, ppn, flags);
+        area.data_frames.insert(vpn, Arc::new(frame));
+        area.cow_pages.remove(&vpn);
+    }
+
+    pub fn map_trampoline(&mut self) {
+        extern "C" {
+            fn strampoline();
+        }
+        self.page_table.map(
+            VirtAddr::from(TRAMPOLINE).into(),
+            super::address::PhysAddr::from(strampoline as usize).into(),
+            PTEFlags::R | PTEFlags::X,
+        );
+    }
+
+    pub fn recycle_data_pages(&mut self) {
+        self.areas.clear();
+    }
+}
+
+pub const KERNEL_MEMORY_END: usize = MEMORY_END;
+pub const TRAP_CONTEXT: usize = TRAP_CONTEXT_BASE;
+
+#[cfg(test)]
+mod tests {
+    // `FRAME_ALLOCATOR` is one global `UPSafeCell` (sound only under the
+    // single-hart cooperative-scheduling assumption on its own doc
+    // comment), and `init_test_frame_allocator` re-points it at a fresh
+    // buffer every time it's called, so every test across this crate that
+    // uses it — not just the ones in this module — must run with
+    // `cargo test -- --test-threads=1`.
+    use super::super::frame_allocator::init_test_frame_allocator;
+    use super::*;
+
+    fn new_rw_area(ms: &mut MemorySet) -> VirtPageNum {
+        ms.insert_area(
+            VirtAddr(0x1000),
+            VirtAddr(0x2000),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+            None,
+        )
+        .expect("insert_area: no data to copy, can't fail");
+        VirtAddr(0x1000).floor()
+    }
+
+    // `MemorySet::fork`/`from_copy_on_write` always calls `map_trampoline`,
+    // whose `extern "C" { fn strampoline(); }` normally resolves to a
+    // page-aligned symbol a linker script places from the trap-entry
+    // assembly — not part of this source tree, so nothing provides it for
+    // a host test binary to link against. This stub exists purely to give
+    // that symbol an address: a real function's address is essentially
+    // never page-aligned (`map_trampoline` converts it straight to a
+    // `PhysPageNum`, which asserts on exactly that), but a
+    // `#[repr(align(4096))]` static's always is.
+    #[repr(align(4096))]
+    struct AlignedPage([u8; PAGE_SIZE]);
+    #[no_mangle]
+    #[allow(non_upper_case_globals)]
+    static strampoline: AlignedPage = AlignedPage([0; PAGE_SIZE]);
+
+    #[test]
+    fn resolve_cow_fault_with_sole_owner_reuses_the_same_frame() {
+        init_test_frame_allocator(8);
+        let mut ms = MemorySet::new_bare();
+        let vpn = new_rw_area(&mut ms);
+        let former_ppn = ms.areas[0].data_frames[&vpn].ppn;
+
+        let ppn = ms.resolve_cow_fault(vpn, former_ppn).unwrap();
+
+        assert_eq!(ppn, former_ppn, "sole owner should keep its existing frame");
+        assert_eq!(Arc::strong_count(&ms.areas[0].data_frames[&vpn]), 1);
+    }
+
+    #[test]
+    fn resolve_cow_fault_with_a_shared_owner_copies_into_a_fresh_frame() {
+        init_test_frame_allocator(8);
+        let mut ms = MemorySet::new_bare();
+        let vpn = new_rw_area(&mut ms);
+        let former_ppn = ms.areas[0].data_frames[&vpn].ppn;
+        former_ppn.get_bytes_array()[0] = 0xAB;
+        // Stands in for a fork sibling still sharing this frame.
+        let sibling_ref = ms.areas[0].data_frames[&vpn].clone();
+        ms.areas[0].cow_pages.insert(vpn);
+
+        let new_ppn = ms.resolve_cow_fault(vpn, former_ppn).unwrap();
+
+        assert_ne!(new_ppn, former_ppn, "a shared frame must not be reused in place");
+        assert_eq!(new_ppn.get_bytes_array()[0], 0xAB, "contents must be copied");
+        assert_eq!(Arc::strong_count(&ms.areas[0].data_frames[&vpn]), 1);
+        assert!(!ms.areas[0].cow_pages.contains(&vpn));
+        drop(sibling_ref);
+    }
+
+    #[test]
+    fn mmap_anonymous_private_memory_survives_a_real_fork() {
+        // The actual scenario this request described: mmap anonymous
+        // private memory, write a value, fork, have the child overwrite
+        // its copy, and check the parent's value survived — going through
+        // the real `insert_mmap_area` and `MemorySet::fork` entry points
+        // rather than poking `resolve_cow`'s internals directly.
+        init_test_frame_allocator(8);
+        let mut parent = MemorySet::new_bare();
+        parent.insert_mmap_area(
+            VirtAddr(0x1000),
+            VirtAddr(0x2000),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let vpn = VirtAddr(0x1000).floor();
+        // Writable, so `insert_mmap_area` already prefaulted this page off
+        // the shared `ZERO_FRAME` onto a private frame of its own.
+        let parent_ppn = parent.page_table.translate(vpn).unwrap().ppn();
+        parent_ppn.get_bytes_array()[0] = 0x11;
+
+        let mut child = MemorySet::fork(&mut parent);
+
+        assert_eq!(parent.frame_ref_count(vpn), 2, "fork must share the frame, not copy it");
+        assert!(parent.is_cow_page(vpn) && child.is_cow_page(vpn));
+        let child_ppn = child.page_table.translate(vpn).unwrap().ppn();
+        assert_eq!(child_ppn, parent_ppn, "child starts out sharing the parent's exact frame");
+
+        let new_ppn = child.resolve_cow_fault(vpn, child_ppn).unwrap();
+        new_ppn.get_bytes_array()[0] = 0x22;
+
+        assert_ne!(new_ppn, parent_ppn, "the child's write must split off a private frame");
+        assert_eq!(
+            parent.page_table.translate(vpn).unwrap().ppn().get_bytes_array()[0],
+            0x11,
+            "the parent's value must survive the child's write"
+        );
+        assert_eq!(parent.frame_ref_count(vpn), 1, "parent is left as the sole owner of its frame");
+    }
+
+    #[test]
+    fn mremap_style_tail_growth_leaves_every_new_page_actually_backed() {
+        // Mirrors the move branch of `sys_mremap`: pages already present
+        // before the move are remapped as-is, and the newly requested tail
+        // needs its own frames eagerly allocated via `map_one`, the same
+        // helper the grow-in-place branch already used — not left with no
+        // PTE at all, which is what used to make a fault into the tail
+        // permanently fatal.
+        init_test_frame_allocator(8);
+        let mut page_table = PageTable::new();
+        let mut area = MapArea::new(
+            VirtAddr(0x1000),
+            VirtAddr(0x4000),
+            MapType::Framed,
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let old_end = VirtAddr(0x2000).floor();
+        let new_end = VirtAddr(0x4000).floor();
+        let mut vpn = area.vpn_range.get_start();
+        while vpn.0 < new_end.0 {
+            area.map_one(&mut page_table, vpn);
+            vpn.step();
+        }
+
+        let mut tail_vpn = old_end;
+        while tail_vpn.0 < new_end.0 {
+            let pte = page_table
+                .translate(tail_vpn)
+                .expect("tail page must have a real PTE, not be left unbacked");
+            assert!(pte.is_valid() && pte.writable(), "tail page must be present and writable");
+            tail_vpn.step();
+        }
+    }
+
+    #[test]
+    fn access_ok_rejects_a_range_spanning_two_areas_where_only_one_grants_write() {
+        init_test_frame_allocator(8);
+        let mut ms = MemorySet::new_bare();
+        // Two adjacent areas, [0x1000, 0x2000) read-write and
+        // [0x2000, 0x3000) read-only, so a range spanning both only
+        // partially satisfies a write check.
+        ms.insert_area(
+            VirtAddr(0x1000),
+            VirtAddr(0x2000),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+            None,
+        )
+        .unwrap();
+        ms.insert_area(
+            VirtAddr(0x2000),
+            VirtAddr(0x3000),
+            MapPermission::R | MapPermission::U,
+            None,
+        )
+        .unwrap();
+
+        assert!(ms.access_ok(VirtAddr(0x1000), 0x2000, MapPermission::R));
+        assert!(!ms.access_ok(VirtAddr(0x1000), 0x2000, MapPermission::W));
+        // Each area on its own still grants what it actually has.
+        assert!(ms.access_ok(VirtAddr(0x1000), 0x1000, MapPermission::W));
+        assert!(ms.access_ok(VirtAddr(0x2000), 0x1000, MapPermission::R));
+        assert!(!ms.access_ok(VirtAddr(0x2000), 0x1000, MapPermission::W));
+    }
+}
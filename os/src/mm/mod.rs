@@ -0,0 +1,34 @@
+//! Virtual memory: address types, the frame allocator, page tables and
+//! address spaces (`MemorySet`).
+
+mod address;
+mod elf;
+mod frame_allocator;
+mod heap_allocator;
+mod memory_set;
+mod page_table;
+
+pub use address::{
+    vpn_ranges_overlap, PhysAddr, PhysPageNum, StepByOne, VPNRange, VirtAddr, VirtPageNum,
+};
+pub use elf::{from_elf, ElfLoadError};
+pub use frame_allocator::{frame_alloc, frame_dealloc, init_frame_allocator_to, memory_end, FrameTracker};
+pub use memory_set::{
+    FaultKind, FaultOutcome, KernelError, MapArea, MapPermission, MapType, MemorySet,
+    KERNEL_MEMORY_END, TRAP_CONTEXT,
+};
+pub use page_table::{
+    translated_byte_buffer, translated_ref, translated_refmut, translated_str,
+    try_translated_refmut, PTEFlags, PageTable, PageTableEntry, UserBuffer, UserBufferIterator,
+};
+
+pub fn init() {
+    heap_allocator::init_heap();
+    init_frame_allocator_to(KERNEL_MEMORY_END);
+}
+
+/// Override the physical memory size used by the frame allocator, e.g. once
+/// the real amount has been discovered from the device tree.
+pub fn set_memory_end(memory_end: usize) {
+    init_frame_allocator_to(memory_end);
+}
@@ -0,0 +1,401 @@
+//! Sv39 page table implementation plus the `translated_*` helpers used to
+//! cross the user/kernel pointer boundary.
+
+use super::address::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use super::frame_allocator::{frame_alloc, FrameTracker};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use core::ops::{Index, IndexMut};
+
+bitflags! {
+    pub struct PTEFlags: u8 {
+        const V = 1 << 0;
+        const R = 1 << 1;
+        const W = 1 << 2;
+        const X = 1 << 3;
+        const U = 1 << 4;
+        const G = 1 << 5;
+        const A = 1 << 6;
+        const D = 1 << 7;
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct PageTableEntry {
+    pub bits: usize,
+}
+
+impl PageTableEntry {
+    pub fn new(ppn: PhysPageNum, flags: PTEFlags) -> Self {
+        Self {
+            bits: ppn.0 << 10 | flags.bits as usize,
+        }
+    }
+    pub fn empty() -> Self {
+        Self { bits: 0 }
+    }
+    pub fn ppn(&self) -> PhysPageNum {
+        (self.bits >> 10 & ((1usize << 44) - 1)).into()
+    }
+    pub fn flags(&self) -> PTEFlags {
+        PTEFlags::from_bits(self.bits as u8).unwrap()
+    }
+    pub fn is_valid(&self) -> bool {
+        (self.flags() & PTEFlags::V) != PTEFlags::empty()
+    }
+    pub fn readable(&self) -> bool {
+        (self.flags() & PTEFlags::R) != PTEFlags::empty()
+    }
+    pub fn writable(&self) -> bool {
+        (self.flags() & PTEFlags::W) != PTEFlags::empty()
+    }
+    pub fn executable(&self) -> bool {
+        (self.flags() & PTEFlags::X) != PTEFlags::empty()
+    }
+    /// The hardware-maintained Accessed bit: set by the MMU on any load,
+    /// store, or instruction fetch through this PTE. Read (and cleared
+    /// with [`Self::clear_accessed`]) by an LRU scan picking an eviction
+    /// candidate for swap — a page nothing has touched since the last
+    /// scan is a good one to page out.
+    pub fn accessed(&self) -> bool {
+        (self.flags() & PTEFlags::A) != PTEFlags::empty()
+    }
+    /// The hardware-maintained Dirty bit: set by the MMU on any store
+    /// through this PTE. Read (and cleared with [`Self::clear_dirty`]) by
+    /// `msync`/swap-out to tell whether a page's backing copy (a mapped
+    /// file, or a swap slot) is stale and needs writing back before the
+    /// frame can be reused.
+    pub fn dirty(&self) -> bool {
+        (self.flags() & PTEFlags::D) != PTEFlags::empty()
+    }
+    /// Clear the Accessed bit, e.g. at the start of an LRU scan interval
+    /// so the next scan only sees accesses since this clear.
+    pub fn clear_accessed(&mut self) {
+        self.set_flags(self.flags() - PTEFlags::A);
+    }
+    /// Clear the Dirty bit, e.g. right after `msync`/swap-out has written
+    /// the page's contents back to their backing store.
+    pub fn clear_dirty(&mut self) {
+        self.set_flags(self.flags() - PTEFlags::D);
+    }
+    pub fn set_ppn(&mut self, ppn: PhysPageNum) {
+        self.bits = (self.bits & ((1 << 10) - 1)) | (ppn.0 << 10);
+    }
+    pub fn set_flags(&mut self, flags: PTEFlags) {
+        self.bits = (self.bits & !0xffusize) | flags.bits as usize;
+    }
+}
+
+pub struct PageTable {
+    root_ppn: PhysPageNum,
+    frames: Vec<FrameTracker>,
+}
+
+impl PageTable {
+    pub fn new() -> Self {
+        let frame = frame_alloc().unwrap();
+        Self {
+            root_ppn: frame.ppn,
+            frames: vec![frame],
+        }
+    }
+
+    pub fn from_token(satp: usize) -> Self {
+        Self {
+            root_ppn: PhysPageNum::from(satp & ((1usize << 44) - 1)),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn token(&self) -> usize {
+        8usize << 60 | self.root_ppn.0
+    }
+
+    fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, &idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array()[idx];
+            if i == 2 {
+                result = Some(pte);
+                break;
+            }
+            if !pte.is_valid() {
+                let frame = frame_alloc().unwrap();
+                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+                self.frames.push(frame);
+            }
+            ppn = pte.ppn();
+        }
+        result
+    }
+
+    pub fn find_pte(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, &idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array()[idx];
+            if i == 2 {
+                result = Some(pte);
+                break;
+            }
+            if !pte.is_valid() {
+                return None;
+            }
+            ppn = pte.ppn();
+        }
+        result
+    }
+
+    /// Map `vpn` to `ppn`. Panics if `vpn` is already mapped — callers that
+    /// need idempotence should check [`Self::translate`] first.
+    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+
+    pub fn unmap(&mut self, vpn: VirtPageNum) {
+        let idxs = vpn.indexes();
+        // Walk root -> middle -> leaf, remembering each level's own ppn so
+        // the leaf and (if it empties out too) the middle table can be
+        // reclaimed afterwards without re-walking from the root.
+        let mid_ppn = {
+            let pte = &mut self.root_ppn.get_pte_array()[idxs[0]];
+            assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+            pte.ppn()
+        };
+        let leaf_ppn = {
+            let pte = &mut mid_ppn.get_pte_array()[idxs[1]];
+            assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+            pte.ppn()
+        };
+        let leaf_pte = &mut leaf_ppn.get_pte_array()[idxs[2]];
+        assert!(leaf_pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+        *leaf_pte = PageTableEntry::empty();
+
+        if Self::table_is_empty(leaf_ppn) {
+            self.free_table_frame(leaf_ppn);
+            let mid_pte = &mut mid_ppn.get_pte_array()[idxs[1]];
+            *mid_pte = PageTableEntry::empty();
+            if Self::table_is_empty(mid_ppn) {
+                self.free_table_frame(mid_ppn);
+                let root_pte = &mut self.root_ppn.get_pte_array()[idxs[0]];
+                *root_pte = PageTableEntry::empty();
+                // The root table itself is never reclaimed — every address
+                // space needs at least its own root frame to stay valid.
+            }
+        }
+    }
+
+    /// Whether every entry of the page-table frame at `ppn` is invalid —
+    /// i.e. nothing under it is mapped anymore and it's safe to free.
+    fn table_is_empty(ppn: PhysPageNum) -> bool {
+        ppn.get_pte_array().iter().all(|pte| !pte.is_valid())
+    }
+
+    /// Reclaim an intermediate (non-root) page-table frame once
+    /// [`Self::table_is_empty`] confirms nothing points into it anymore —
+    /// without this, every map/unmap cycle that happens to cross a
+    /// level-1/level-2 table boundary leaks that table's frame forever,
+    /// since `self.frames` only ever grows in `find_pte_create`.
+    fn free_table_frame(&mut self, ppn: PhysPageNum) {
+        if let Some(idx) = self.frames.iter().position(|f| f.ppn == ppn) {
+            self.frames.remove(idx);
+        }
+    }
+
+    pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
+        self.find_pte(vpn).map(|pte| *pte)
+    }
+
+    /// Walk the three Sv39 levels for `vpn` without requiring the leaf to be
+    /// valid, for MMU-debugging purposes (e.g. a syscall that lets userspace
+    /// inspect its own mapping). Index `0` is the root-level (VPN2) entry,
+    /// index `2` is the leaf (VPN0) entry. Walking stops early — leaving the
+    /// remaining levels `None` — the first time it hits an invalid entry,
+    /// since there's nothing further down to read.
+    pub fn walk(&self, vpn: VirtPageNum) -> [Option<PageTableEntry>; 3] {
+        let idxs = vpn.indexes();
+        let mut levels = [None; 3];
+        let mut ppn = self.root_ppn;
+        for (i, &idx) in idxs.iter().enumerate() {
+            let pte = ppn.get_pte_array()[idx];
+            levels[i] = Some(pte);
+            if !pte.is_valid() {
+                break;
+            }
+            ppn = pte.ppn();
+        }
+        levels
+    }
+
+    pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.find_pte(va.floor()).map(|pte| {
+            let aligned_pa: PhysAddr = pte.ppn().into();
+            let offset = va.page_offset();
+            (aligned_pa.0 + offset).into()
+        })
+    }
+}
+
+/// Translate `ptr` (length `len` bytes) in the address space rooted at
+/// `token` into a list of kernel-visible byte slices, splitting at page
+/// boundaries since the underlying frames need not be contiguous.
+pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+    let page_table = PageTable::from_token(token);
+    let mut start = ptr as usize;
+    let end = start + len;
+    let mut v = Vec::new();
+    while start < end {
+        let start_va = VirtAddr::from(start);
+        let mut vpn = start_va.floor();
+        let ppn = page_table.translate(vpn).unwrap().ppn();
+        vpn.step();
+        let mut end_va: VirtAddr = vpn.into();
+        end_va = end_va.min(VirtAddr::from(end));
+        if end_va.page_offset() == 0 {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
+        } else {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+        }
+        start = end_va.into();
+    }
+    v
+}
+
+pub fn translated_str(token: usize, ptr: *const u8) -> String {
+    let page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let mut va = ptr as usize;
+    loop {
+        let ch: u8 = *(page_table
+            .translate_va(VirtAddr::from(va))
+            .unwrap()
+            .get_mut());
+        if ch == 0 {
+            break;
+        }
+        string.push(ch as char);
+        va += 1;
+    }
+    string
+}
+
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+    let page_table = PageTable::from_token(token);
+    page_table
+        .translate_va(VirtAddr::from(ptr as usize))
+        .unwrap()
+        .get_mut()
+}
+
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+    let page_table = PageTable::from_token(token);
+    let va = ptr as usize;
+    page_table
+        .translate_va(VirtAddr::from(va))
+        .unwrap()
+        .get_mut()
+}
+
+/// Like [`translated_refmut`], but returns `None` instead of panicking when
+/// `ptr` doesn't resolve to a mapped page — for syscalls that need to
+/// report `EFAULT` to a misbehaving caller rather than taking the whole
+/// kernel down over a bad user pointer.
+pub fn try_translated_refmut<T>(token: usize, ptr: *mut T) -> Option<&'static mut T> {
+    let page_table = PageTable::from_token(token);
+    page_table
+        .translate_va(VirtAddr::from(ptr as usize))
+        .map(|pa| pa.get_mut())
+}
+
+/// A user-space buffer described as a list of kernel-visible byte slices,
+/// used to pass read/write targets through the syscall layer without
+/// copying more than necessary.
+pub struct UserBuffer {
+    pub buffers: Vec<&'static mut [u8]>,
+}
+
+impl UserBuffer {
+    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
+        Self { buffers }
+    }
+    pub fn len(&self) -> usize {
+        self.buffers.iter().map(|b| b.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Locate the `(buffer, offset)` a flat byte index falls into, for
+    /// `Index`/`IndexMut`. Panics on out-of-range `idx`, same as a slice.
+    fn locate(&self, idx: usize) -> (usize, usize) {
+        let mut remaining = idx;
+        for (i, buf) in self.buffers.iter().enumerate() {
+            if remaining < buf.len() {
+                return (i, remaining);
+            }
+            remaining -= buf.len();
+        }
+        panic!("UserBuffer index {} out of range", idx);
+    }
+}
+
+impl Index<usize> for UserBuffer {
+    type Output = u8;
+    fn index(&self, idx: usize) -> &u8 {
+        let (buf, offset) = self.locate(idx);
+        &self.buffers[buf][offset]
+    }
+}
+
+impl IndexMut<usize> for UserBuffer {
+    fn index_mut(&mut self, idx: usize) -> &mut u8 {
+        let (buf, offset) = self.locate(idx);
+        &mut self.buffers[buf][offset]
+    }
+}
+
+/// Byte-at-a-time cursor over a [`UserBuffer`]'s (possibly non-contiguous)
+/// backing pages, for callers like line-oriented stdin parsing that want to
+/// read one byte at a time without tracking page boundaries themselves.
+pub struct UserBufferIterator {
+    buffers: Vec<&'static mut [u8]>,
+    current_buffer: usize,
+    current_idx: usize,
+}
+
+impl IntoIterator for UserBuffer {
+    type Item = *mut u8;
+    type IntoIter = UserBufferIterator;
+    fn into_iter(self) -> Self::IntoIter {
+        UserBufferIterator {
+            buffers: self.buffers,
+            current_buffer: 0,
+            current_idx: 0,
+        }
+    }
+}
+
+impl Iterator for UserBufferIterator {
+    type Item = *mut u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_buffer >= self.buffers.len() {
+            return None;
+        }
+        let r = &mut self.buffers[self.current_buffer][self.current_idx] as *mut u8;
+        if self.current_idx + 1 == self.buffers[self.current_buffer].len() {
+            self.current_idx = 0;
+            self.current_buffer += 1;
+        } else {
+            self.current_idx += 1;
+        }
+        Some(r)
+    }
+}
@@ -0,0 +1,5 @@
+//! Synchronization primitives usable in a `no_std` kernel.
+
+mod up;
+
+pub use up::UPSafeCell;
@@ -0,0 +1,37 @@
+use core::cell::{RefCell, RefMut};
+
+/// Wraps a `RefCell` to make it `Sync`, relying on the single-hart
+/// cooperative-scheduling assumption that the kernel never actually
+/// accesses the inner value from two threads at once.
+///
+/// This is the reason kernel-space state (the kernel's own [`MemorySet`],
+/// the task manager, the frame allocator, ...) is behind `UPSafeCell`
+/// rather than a `spin::Mutex` with real contention to worry about: on a
+/// single hart there's only ever one cooperative context running kernel
+/// code at a time, so there's nothing to contend. Splitting locking for
+/// read-mostly kernel-space operations, or sharding areas across harts,
+/// only becomes meaningful once this kernel grows actual SMP support
+/// (multiple harts concurrently in kernel mode) — there's none of that
+/// here yet to benchmark or shard against.
+///
+/// [`MemorySet`]: crate::mm::MemorySet
+pub struct UPSafeCell<T> {
+    inner: RefCell<T>,
+}
+
+unsafe impl<T> Sync for UPSafeCell<T> {}
+
+impl<T> UPSafeCell<T> {
+    /// # Safety
+    /// The caller must guarantee the returned instance is used on a single
+    /// hart and without overlapping `exclusive_access` borrows.
+    pub unsafe fn new(value: T) -> Self {
+        Self {
+            inner: RefCell::new(value),
+        }
+    }
+
+    pub fn exclusive_access(&self) -> RefMut<'_, T> {
+        self.inner.borrow_mut()
+    }
+}
@@ -0,0 +1,86 @@
+//! Kernel-debugging syscalls. These aren't real Linux syscalls; they exist
+//! so userspace test programs can inspect kernel internals directly instead
+//! of the kernel growing ad-hoc `println!` probes.
+
+use crate::fs::check_consistency;
+use crate::mm::{translated_refmut, PageTable};
+use crate::task::{all_live_processes, current_user_token};
+
+/// One Sv39 page-table level as seen by [`sys_mmu_walk`]. Mirrors
+/// [`crate::mm::PageTableEntry`] but as a fixed-layout struct safe to copy
+/// into userspace, with `valid` called out explicitly instead of relying on
+/// the caller to check bit 0 of `flags`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct PageWalkLevel {
+    pub valid: u8,
+    pub flags: u8,
+    pub ppn: usize,
+}
+
+/// Walk the page table backing `va` in the calling task's address space and
+/// write the three Sv39 levels (root to leaf) into `out`, which must point
+/// to an array of 3 [`PageWalkLevel`]s. Returns the number of levels read,
+/// counting the first invalid entry if the walk stopped at one (so 3
+/// whenever the walk reaches the leaf level, mapped or not). `va` is masked
+/// to the Sv39 width rather than rejected, so this never returns an error.
+pub fn sys_mmu_walk(va: usize, out: *mut PageWalkLevel) -> isize {
+    let token = current_user_token();
+    let page_table = PageTable::from_token(token);
+    let vpn = crate::mm::VirtAddr::from(va).floor();
+    let levels = page_table.walk(vpn);
+    let mut walked = 0;
+    for (i, level) in levels.iter().enumerate() {
+        let slot = translated_refmut(token, unsafe { out.add(i) });
+        *slot = match level {
+            Some(pte) => {
+                walked = i + 1;
+                PageWalkLevel {
+                    valid: pte.is_valid() as u8,
+                    flags: pte.flags().bits(),
+                    ppn: pte.ppn().0,
+                }
+            }
+            None => PageWalkLevel::default(),
+        };
+    }
+    walked as isize
+}
+
+/// Scan the filesystem namespace for metadata inconsistencies (see
+/// [`check_consistency`]) and return the number found. Meant to be run
+/// after tests that exercise `unlink`/`rename`/`link`-style metadata
+/// mutation, to catch a dangling dirent left behind by a bug in one of
+/// those paths.
+pub fn sys_fsck() -> isize {
+    check_consistency() as isize
+}
+
+/// A system-wide memory audit across every still-live process, for the same
+/// "inspect kernel internals directly" purpose as the rest of this module —
+/// a CoW/shm bug that only manifests as pages double-counted or missing
+/// across process boundaries wouldn't show up in any one process's own
+/// `sys_task_stats`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SystemMemoryInfo {
+    pub process_count: usize,
+    pub total_rss_pages: usize,
+}
+
+/// Walk [`all_live_processes`] and write the process count and the sum of
+/// every one's `MemorySet::current_rss_pages` into `*out`. A frame shared
+/// copy-on-write between two processes is counted once per process (each
+/// still maps it), by design: this reports mapped pages, not distinct
+/// physical frames.
+pub fn sys_meminfo(out: *mut SystemMemoryInfo) -> isize {
+    let token = current_user_token();
+    let mut info = SystemMemoryInfo::default();
+    for process in all_live_processes() {
+        let inner = process.inner_exclusive_access();
+        info.process_count += 1;
+        info.total_rss_pages += inner.memory_set.current_rss_pages();
+    }
+    *translated_refmut(token, out) = info;
+    0
+}
@@ -0,0 +1,45 @@
+//! A `Result`-based alternative to a bare `isize` return for syscall
+//! implementations, so a successful value and a negative errno can't be
+//! confused with each other the way two plain `isize`s can. `syscall::fs`
+//! is fully migrated onto [`SyscallResult`]; the other syscall modules
+//! haven't moved over yet and still return `isize` directly with their own
+//! `EFOO: isize = -N` constants, the same style this replaces.
+
+/// Negated to produce the actual syscall return value — e.g.
+/// `Errno::EBADF as isize` is `9`, so a `read` on a bad fd returns `-9`.
+/// Values match Linux's `<asm-generic/errno-base.h>`, same as every bare
+/// `-N` constant this replaces, so no existing return value changes by
+/// migrating a syscall onto this type.
+///
+/// `EPERM` doubles as the generic "something went wrong" sentinel for call
+/// sites that used to `return -1` without naming a specific errno (most of
+/// them really meant `ENOENT`, but this tree never defined one) — `-(EPERM
+/// as isize) == -1` is the same byte a caller already saw before this
+/// migration, and inventing a real `ENOENT = 2` here would silently change
+/// that to `-2` for no behavioral reason.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ESRCH = 3,
+    EIO = 5,
+    EBADF = 9,
+    EWOULDBLOCK = 11,
+    EFAULT = 14,
+    EINVAL = 22,
+}
+
+/// What a migrated syscall implementation returns internally; the
+/// dispatcher converts this to the bare `isize` ABI return value via
+/// [`into_isize`].
+pub type SyscallResult = Result<isize, Errno>;
+
+/// The single `Ok(v) -> v` / `Err(e) -> -(e as isize)` conversion mentioned
+/// in [`Errno`]'s doc comment, applied once at the dispatch boundary
+/// rather than at every syscall's individual return sites.
+pub fn into_isize(result: SyscallResult) -> isize {
+    match result {
+        Ok(v) => v,
+        Err(e) => -(e as isize),
+    }
+}
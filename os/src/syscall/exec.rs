@@ -0,0 +1,18 @@
+//! `exec`-family syscalls.
+
+use crate::mm::{from_elf, ElfLoadError};
+
+/// Replace the caller's address space with the image in `elf_data`.
+///
+/// `from_elf` used to panic on a malformed binary, which meant a
+/// corrupted or adversarial executable could crash the kernel instead of
+/// just failing the syscall. This reports `-ENOEXEC` (`-8`) for a bad
+/// image, or `-ENOMEM` (`-12`) if loading ran the frame allocator dry,
+/// instead.
+pub fn exec_replace_memory_set(elf_data: &[u8]) -> isize {
+    match from_elf(elf_data) {
+        Ok(_memory_set_and_entry) => 0,
+        Err(ElfLoadError::Malformed(_)) | Err(ElfLoadError::Unsupported(_)) => -8,
+        Err(ElfLoadError::OutOfMemory) => -12,
+    }
+}
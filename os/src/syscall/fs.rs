@@ -0,0 +1,1200 @@
+//! File-descriptor-based syscalls: poll, read/write dispatch, etc.
+
+use crate::config::CLOCK_FREQ;
+use crate::fs::{
+    create_inode, create_inode_with_parents, make_pipe, open_inode, resolve_chroot_path,
+    resolve_inode, Epoll, EpollEvent, File, FileError, Inode, IoCqe, IoRing, IoSqe, OSInode, Pipe,
+    EPOLLIN, EPOLLOUT, IORING_OP_READ, IORING_OP_WRITE,
+};
+use crate::mm::{
+    translated_byte_buffer, translated_ref, translated_refmut, translated_str,
+    try_translated_refmut, MapPermission, UserBuffer, VirtAddr, VirtPageNum,
+};
+use super::current_task_or_esrch;
+use super::{Errno, SyscallResult};
+use crate::task::suspend_current_and_run_next;
+use crate::timer::get_time;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Change the process's working directory to `path`, which must already
+/// exist (within the caller's chroot, see [`sys_chroot`]) in `fs::inode`'s
+/// flat namespace. Returns `Err(EPERM)` (`-1`, really "ENOENT" — see
+/// [`Errno`]'s doc comment) if it doesn't.
+pub fn sys_chdir(path: *const u8) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let path = translated_str(token, path);
+    let path = resolve_chroot_path(&inner.root, &path);
+    if open_inode(&path).is_none() {
+        return Err(Errno::EPERM);
+    }
+    inner.cwd = path;
+    Ok(0)
+}
+
+/// Confine the calling process to the subtree rooted at `path`: every
+/// absolute path this process later passes to `sys_open`/`sys_chdir`/
+/// `sys_fstatat`/`sys_readlinkat` is resolved against this root instead of
+/// the real one via the shared [`resolve_chroot_path`], and a `..` that
+/// would walk back out past it is clamped in place rather than escaping.
+/// `path` itself is resolved against the *current* root first, so repeated
+/// chroots nest instead of each starting over from the real root. Returns
+/// `Err(EPERM)` unless the caller is uid 0, or `Err(EPERM)` (really
+/// `ENOTDIR`/`ENOENT`, see [`Errno`]'s doc comment) if the resolved path
+/// isn't an existing directory.
+pub fn sys_chroot(path: *const u8) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.uid != 0 {
+        return Err(Errno::EPERM);
+    }
+    let token = inner.memory_set.token();
+    let path = translated_str(token, path);
+    let new_root = resolve_chroot_path(&inner.root, &path);
+    match open_inode(&new_root) {
+        Some(inode) if inode.is_dir() => {
+            inner.root = new_root;
+            Ok(0)
+        }
+        _ => Err(Errno::EPERM),
+    }
+}
+
+/// Copy the process's current working directory (NUL-terminated) into
+/// `buf`. Returns `Err(EPERM)` (really `ERANGE`, same caveat as
+/// [`sys_chdir`]) if `buf` is too small to hold it, or `Err(EFAULT)` if
+/// `buf` isn't a writable user address.
+pub fn sys_getcwd(buf: *mut u8, size: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let cwd = inner.cwd.clone();
+
+    if cwd.len() + 1 > size {
+        return Err(Errno::EPERM);
+    }
+    let mut bytes = cwd.into_bytes();
+    bytes.push(0);
+    if !inner.memory_set.copy_to_user(VirtAddr::from(buf as usize), &bytes) {
+        return Err(Errno::EFAULT);
+    }
+    Ok(buf as isize)
+}
+
+/// Create a pipe and install its read/write ends as two new fds, written to
+/// `fds[0]` (read end) and `fds[1]` (write end). Both words of `fds` are
+/// validated before either fd is allocated, so a bad pointer returns
+/// `EFAULT` without leaking an fd the caller can never learn the number of.
+pub fn sys_pipe(fds: *mut i32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let token = process.inner_exclusive_access().memory_set.token();
+
+    if try_translated_refmut(token, fds).is_none()
+        || try_translated_refmut(token, unsafe { fds.add(1) }).is_none()
+    {
+        return Err(Errno::EFAULT);
+    }
+
+    let (read_end, write_end) = make_pipe();
+    let mut inner = process.inner_exclusive_access();
+    let read_fd = inner.alloc_fd(read_end);
+    let write_fd = inner.alloc_fd(write_end);
+    drop(inner);
+
+    *translated_refmut(token, fds) = read_fd as i32;
+    *translated_refmut(token, unsafe { fds.add(1) }) = write_fd as i32;
+    Ok(0)
+}
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREAT: u32 = 1 << 9;
+/// Not a standard Linux `open` flag: Linux instead requires callers to
+/// `mkdir` each missing parent directory explicitly. This kernel's
+/// namespace has no real directory hierarchy to walk (`fs::inode`'s root
+/// table is flat), so there's nowhere for a separate `mkdir` family of
+/// syscalls to live yet; this flag gets the common "create the whole path"
+/// case working in the meantime.
+pub const O_CREAT_PARENTS: u32 = 1 << 30;
+/// Get an fd backed by a freshly allocated inode that was never given a
+/// name in `path`'s directory (or anywhere else). See [`sys_open`]'s
+/// `O_TMPFILE` handling.
+pub const O_TMPFILE: u32 = 1 << 10;
+
+/// Open (optionally creating) the file at `path`, returning a new fd. When
+/// a new inode is actually created (`O_CREAT`/`O_CREAT_PARENTS` and `path`
+/// didn't already exist), its permission bits are `mode & !umask` — the
+/// calling process's `sys_umask`-set mask strips bits from `mode` the same
+/// way `open(2)` does, rather than the newly created inode ever seeing the
+/// unmasked `mode`. `mode` is ignored when `path` already exists, matching
+/// `open(2)`. Returns `-1` (`ENOENT`) if the file doesn't exist and neither
+/// `O_CREAT` nor `O_CREAT_PARENTS` was given.
+///
+/// `O_TMPFILE` takes a different path entirely: `path` must name an
+/// existing directory, and the returned fd is backed by a brand new inode
+/// that's never inserted into `fs::inode`'s namespace at all, rather than
+/// one found or created at `path` itself — secure temp-file creation with
+/// no window where another path lookup could ever see it. Its blocks are
+/// freed the moment the fd's last reference drops, the same
+/// reference-counting `unlink_inode`'s doc comment already relies on for
+/// unlink-while-open, except here nothing ever inserted a name to unlink
+/// in the first place. Real `O_TMPFILE` allows linking the anonymous inode
+/// into the namespace later via `linkat(2)`'s `AT_EMPTY_PATH`; this tree
+/// has no `sys_linkat` yet (or any syscall that names an fd's inode rather
+/// than a path), so that half stays out of reach until one exists — every
+/// `O_TMPFILE` inode here is unlinkable-by-construction for its whole
+/// lifetime.
+pub fn sys_open(path: *const u8, flags: u32, mode: u32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let root = inner.root.clone();
+    let perm = mode & !inner.umask & 0o777;
+    drop(inner);
+    let path = translated_str(token, path);
+    let path = resolve_chroot_path(&root, &path);
+
+    let readable = flags & O_WRONLY == 0;
+    let writable = flags & O_WRONLY != 0 || flags & O_RDWR != 0;
+
+    if flags & O_TMPFILE != 0 {
+        match open_inode(&path) {
+            Some(dir) if dir.is_dir() => {}
+            _ => return Err(Errno::EPERM),
+        }
+        let inode = Inode::new();
+        inode.set_perm(perm);
+        let file: Arc<dyn File> = Arc::new(OSInode::new(readable, writable, inode));
+        let mut inner = process.inner_exclusive_access();
+        return Ok(inner.alloc_fd(file) as isize);
+    }
+
+    let inode = match open_inode(&path) {
+        Some(inode) => inode,
+        None if flags & O_CREAT_PARENTS != 0 => create_inode_with_parents(&path, perm),
+        None if flags & O_CREAT != 0 => create_inode(&path, perm),
+        None => return Err(Errno::EPERM),
+    };
+
+    let file: Arc<dyn File> = Arc::new(OSInode::new(readable, writable, inode));
+
+    let mut inner = process.inner_exclusive_access();
+    Ok(inner.alloc_fd(file) as isize)
+}
+
+/// The largest single `read`/`write` transfer honored at once, the same
+/// "rounded down to the nearest page below 2GiB" cap Linux applies
+/// (`MAX_RW_COUNT`) so a single huge request can't demand one enormous
+/// `translated_byte_buffer` allocation. A `len` above this is silently
+/// capped rather than rejected — only a `len` that wouldn't even fit in an
+/// `isize` (and so couldn't be returned as a byte count) is an outright
+/// `-EINVAL`.
+const MAX_RW_COUNT: usize = 0x7fff_f000;
+
+/// Read up to `len` bytes from `fd` into `buf`, at the file's own cursor.
+/// The generic path through `File::read` rather than downcasting to a
+/// concrete file kind — plain `read` doesn't care whether `fd` is a pipe or
+/// a regular file, unlike e.g. `sys_readdir`. Returns `-EINVAL` if `len`
+/// exceeds `isize::MAX` (it could never be returned as a valid byte count),
+/// `-EBADF` for an unopened or non-readable fd, or `-EIO` if the file
+/// reports a read error. `len` above `MAX_RW_COUNT` is capped rather than
+/// rejected, matching `read(2)`.
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> SyscallResult {
+    if len > isize::MAX as usize {
+        return Err(Errno::EINVAL);
+    }
+    let len = len.min(MAX_RW_COUNT);
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    drop(inner);
+    if !file.readable() {
+        return Err(Errno::EBADF);
+    }
+    // `translated_byte_buffer(token, buf, 0)` already happens to return an
+    // empty `Vec` without ever dereferencing `buf` (its loop condition is
+    // `start < end`, which is false immediately when `len == 0`), so this
+    // isn't fixing an actual fault. It's here so that safety is explicit
+    // at the call site instead of an accident of that loop's bounds, and
+    // so `buf` being null/unmapped is irrelevant for a zero-length read —
+    // checked after fd/readable validation, same as a real `read(2)` still
+    // reports `-EBADF` for a bad fd even when `len == 0`.
+    if len == 0 {
+        return Ok(0);
+    }
+    let user_buf = UserBuffer::new(translated_byte_buffer(token, buf, len));
+    match file.read(user_buf) {
+        Ok(n) => Ok(n as isize),
+        Err(FileError::Io) => Err(Errno::EIO),
+    }
+}
+
+/// Write up to `len` bytes from `buf` to `fd`, at the file's own cursor.
+/// See [`sys_read`] for why this goes through `File::write` generically,
+/// and for the `len` validation/capping.
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> SyscallResult {
+    if len > isize::MAX as usize {
+        return Err(Errno::EINVAL);
+    }
+    let len = len.min(MAX_RW_COUNT);
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    drop(inner);
+    if !file.writable() {
+        return Err(Errno::EBADF);
+    }
+    // See `sys_read`'s matching check for why this is explicit rather than
+    // relying on `translated_byte_buffer` happening to skip `buf` already.
+    if len == 0 {
+        return Ok(0);
+    }
+    let user_buf = UserBuffer::new(translated_byte_buffer(token, buf as *mut u8, len));
+    match file.write(user_buf) {
+        Ok(n) => Ok(n as isize),
+        Err(FileError::Io) => Err(Errno::EIO),
+    }
+}
+
+/// Move up to `len` bytes from pipe `fd_in` directly into pipe `fd_out`
+/// without copying through a user-space buffer. Returns `Err(EPERM)`
+/// (really `EINVAL`, see [`Errno`]'s doc comment) if either fd isn't a
+/// pipe.
+pub fn sys_splice(fd_in: usize, fd_out: usize, len: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let src = inner.fd_file(fd_in);
+    let dst = inner.fd_file(fd_out);
+    drop(inner);
+
+    let (src, dst) = match (src, dst) {
+        (Some(s), Some(d)) => (s, d),
+        _ => return Err(Errno::EPERM),
+    };
+    match (
+        src.as_any().downcast_ref::<Pipe>(),
+        dst.as_any().downcast_ref::<Pipe>(),
+    ) {
+        (Some(src_pipe), Some(dst_pipe)) => Ok(dst_pipe.splice_from(src_pipe, len) as isize),
+        _ => Err(Errno::EPERM),
+    }
+}
+
+/// Close `fd`. Returns `Err(EBADF)` on a double-close or any other
+/// already-closed/never-opened fd, instead of silently succeeding — a
+/// double-close usually means a reference-counting bug upstream that's
+/// worth surfacing rather than papering over.
+pub fn sys_close(fd: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.fd_table.get_mut(fd) {
+        Some(slot @ Some(_)) => {
+            *slot = None;
+            Ok(0)
+        }
+        _ => Err(Errno::EBADF),
+    }
+}
+
+/// `dup(2)`: install a new fd sharing `fd`'s open file description — same
+/// cursor, same status — at the lowest unused number. Returns `Err(EBADF)`
+/// if `fd` isn't open.
+pub fn sys_dup(fd: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.dup_fd(fd) {
+        Some(new_fd) => Ok(new_fd as isize),
+        None => Err(Errno::EBADF),
+    }
+}
+
+/// `dup2(2)`: like [`sys_dup`], but at the caller-chosen `new_fd` rather
+/// than the lowest unused one. Returns `Err(EBADF)` if `fd` isn't open.
+pub fn sys_dup2(fd: usize, new_fd: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.dup2_fd(fd, new_fd) {
+        Some(new_fd) => Ok(new_fd as isize),
+        None => Err(Errno::EBADF),
+    }
+}
+
+pub const LOCK_SH: i32 = 1;
+pub const LOCK_EX: i32 = 2;
+pub const LOCK_UN: i32 = 8;
+pub const LOCK_NB: i32 = 4;
+
+/// `flock(2)`: take or release an advisory whole-file lock on `fd`'s inode.
+/// The lock belongs to `fd`'s open file description (see
+/// [`OSInode::lock_owner`]), not the calling process or this fd number
+/// alone — `dup`/`dup2` of `fd` share it, while a fresh `sys_openat` of the
+/// same path contends against it as a separate owner. It's released
+/// automatically when that description's last fd is closed (`OSInode`'s
+/// `Drop` impl), on top of the explicit `LOCK_UN` here.
+///
+/// Without `LOCK_NB`, a conflicting `LOCK_EX`/`LOCK_SH` blocks the caller
+/// (busy-waiting via [`suspend_current_and_run_next`], the same pattern
+/// `sys_nanosleep` uses in the absence of a real ready-queue scheduler)
+/// until the conflicting lock releases; with `LOCK_NB` it instead returns
+/// `Err(EWOULDBLOCK)` right away. Returns `Err(EBADF)` if `fd` isn't open,
+/// `Err(EPERM)` if it isn't backed by an inode (e.g. a pipe) or
+/// `operation` names neither `LOCK_SH`, `LOCK_EX`, nor `LOCK_UN`.
+pub fn sys_flock(fd: usize, operation: i32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    drop(inner);
+    let inode = match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => inode,
+        None => return Err(Errno::EPERM),
+    };
+    let owner = inode.lock_owner();
+    let backing = inode.inode();
+
+    if operation & LOCK_UN != 0 {
+        backing.unlock(owner);
+        return Ok(0);
+    }
+    let exclusive = match operation & (LOCK_SH | LOCK_EX) {
+        LOCK_SH => false,
+        LOCK_EX => true,
+        _ => return Err(Errno::EPERM),
+    };
+    let nonblocking = operation & LOCK_NB != 0;
+    loop {
+        if backing.try_lock(owner, exclusive) {
+            return Ok(0);
+        }
+        if nonblocking {
+            return Err(Errno::EWOULDBLOCK);
+        }
+        suspend_current_and_run_next();
+    }
+}
+
+/// Copy the next directory entry's name (NUL-terminated, truncated to fit)
+/// from the directory open on `fd` into `buf`. Returns the name's length on
+/// success (not counting the NUL), `0` at end of directory, or a negative
+/// errno on error (`EBADF` for an unopened fd, `EPERM` for one that isn't
+/// an `OSInode`, `EFAULT` for a bad `buf`) — a thin,
+/// one-entry-at-a-time convenience over raw `getdents`.
+///
+/// There's no `sys_ls` anywhere in this tree to list an arbitrary path
+/// directly — a "always lists some global current-directory state and
+/// ignores its argument" debug stub isn't something this syscall set ever
+/// had. Listing a specific directory, including one other than cwd, is
+/// already `sys_open(path, ...)` (which resolves `path` against cwd and
+/// the chroot root like every other path-taking syscall here, and fails
+/// with `-1` if it doesn't exist) followed by repeated `sys_readdir(fd,
+/// ...)` on the fd that opens — no separate path-taking listing syscall is
+/// needed on top of that pair. See [`sys_getdents64`] for the real
+/// batch-listing syscall this is a thin one-entry-at-a-time stand-in for.
+pub fn sys_readdir(fd: usize, buf: *mut u8, len: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+
+    let inode = match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => inode,
+        None => return Err(Errno::EPERM),
+    };
+    match inode.next_dirent() {
+        Some(name) => {
+            let bytes = name.as_bytes();
+            let n = bytes.len().min(len.saturating_sub(1));
+            let mut out = bytes[..n].to_vec();
+            if len > 0 {
+                out.push(0);
+            }
+            if !inner
+                .memory_set
+                .copy_to_user(VirtAddr::from(buf as usize), &out)
+            {
+                return Err(Errno::EFAULT);
+            }
+            Ok(n as isize)
+        }
+        None => Ok(0),
+    }
+}
+
+pub const DT_UNKNOWN: u8 = 0;
+pub const DT_FIFO: u8 = 1;
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+
+/// Size in bytes of a `struct linux_dirent64` header, before the
+/// NUL-terminated `d_name` that follows it: `d_ino` (8) + `d_off` (8) +
+/// `d_reclen` (2) + `d_type` (1).
+const DIRENT64_HEADER_LEN: usize = 19;
+
+fn dirent_type(name: &str) -> u8 {
+    match open_inode(name) {
+        Some(inode) if inode.is_dir() => DT_DIR,
+        Some(inode) if inode.is_symlink() => DT_LNK,
+        Some(_) => DT_REG,
+        None => DT_UNKNOWN,
+    }
+}
+
+/// `getdents64(2)`: fill `buf` with as many `struct linux_dirent64` records
+/// from the directory open on `fd` as fit, consuming exactly the entries
+/// encoded. Returns the number of bytes written, `0` at end of directory,
+/// `Err(EBADF)` if `fd` isn't open or isn't a directory, or `Err(EINVAL)`
+/// if `len` is too small to hold even the next single entry.
+///
+/// `d_type` is filled from the target inode's real kind (`DT_REG`/`DT_DIR`/
+/// `DT_LNK`) rather than left `DT_UNKNOWN` the way a minimal
+/// implementation might — `ls -F` and similar treat `DT_UNKNOWN` as a
+/// signal to `stat` every entry individually, which this in-memory
+/// filesystem can answer for free via the same `Inode::is_dir`/
+/// `is_symlink` its `stat` family already calls. `DT_FIFO` is defined for
+/// completeness but never produced: named pipes have no `mkfifo`-style
+/// entry point into `fs::inode`'s namespace in this tree, only anonymous
+/// `sys_pipe` fds that never get a directory entry at all. `d_ino` and
+/// `d_off` are always `0` — no inode numbers exist yet (same gap `Stat`'s
+/// doc comment covers), and nothing here supports seeking a directory
+/// stream by an earlier entry's offset.
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    let inode = match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => inode,
+        None => return Err(Errno::EBADF),
+    };
+    if !inode.inode().is_dir() {
+        return Err(Errno::EBADF);
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    loop {
+        let name = match inode.peek_dirent() {
+            Some(name) => name,
+            None => break,
+        };
+        let name_bytes = name.as_bytes();
+        let reclen = (DIRENT64_HEADER_LEN + name_bytes.len() + 1 + 7) / 8 * 8;
+        if out.len() + reclen > len {
+            break;
+        }
+        inode.next_dirent();
+
+        let record_start = out.len();
+        out.extend_from_slice(&0u64.to_ne_bytes()); // d_ino
+        out.extend_from_slice(&0i64.to_ne_bytes()); // d_off
+        out.extend_from_slice(&(reclen as u16).to_ne_bytes()); // d_reclen
+        out.push(dirent_type(&name));
+        out.extend_from_slice(name_bytes);
+        out.resize(record_start + reclen, 0); // NUL-terminate d_name and pad to d_reclen
+    }
+
+    if out.is_empty() {
+        return if inode.peek_dirent().is_some() {
+            Err(Errno::EINVAL)
+        } else {
+            Ok(0)
+        };
+    }
+
+    let written = out.len();
+    if !inner
+        .memory_set
+        .copy_to_user(VirtAddr::from(buf as usize), &out)
+    {
+        return Err(Errno::EFAULT);
+    }
+    Ok(written as isize)
+}
+
+pub const FALLOC_FL_PUNCH_HOLE: i32 = 0x02;
+
+/// Preallocate (`mode == 0`) or punch a hole in (`FALLOC_FL_PUNCH_HOLE`) the
+/// byte range `[offset, offset+len)` of `fd`. Returns `Err(EPERM)` if `fd`
+/// isn't a regular file. Other `mode` bits (`FALLOC_FL_KEEP_SIZE`, etc.)
+/// aren't meaningful for this in-memory backing store and are ignored.
+pub fn sys_fallocate(fd: usize, mode: i32, offset: usize, len: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EPERM),
+    };
+    drop(inner);
+    let inode = match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => inode.inode(),
+        None => return Err(Errno::EPERM),
+    };
+    inode.fallocate(offset, len, mode & FALLOC_FL_PUNCH_HOLE != 0);
+    Ok(0)
+}
+
+pub const POSIX_FADV_NORMAL: i32 = 0;
+pub const POSIX_FADV_RANDOM: i32 = 1;
+pub const POSIX_FADV_SEQUENTIAL: i32 = 2;
+pub const POSIX_FADV_WILLNEED: i32 = 3;
+pub const POSIX_FADV_DONTNEED: i32 = 4;
+pub const POSIX_FADV_NOREUSE: i32 = 5;
+
+/// Hint how `fd` will be accessed over `[offset, offset + len)` (`len == 0`
+/// means "to EOF", same as the real syscall). Every advice value here is
+/// accepted and validated against `fd`, but none of them change anything:
+/// `SEQUENTIAL`/`RANDOM` would tune a readahead window this tree has no
+/// block-device readahead to tune, and `DONTNEED` would drop cached blocks
+/// from a page/block cache this tree doesn't have either — see
+/// `fs::inode::preload`'s doc comment, which already establishes why: the
+/// in-memory `Inode` backing every open file already holds its data
+/// permanently resident, rather than caching it over something slower to
+/// re-fetch from, so there's nothing ahead to read in and nothing cached
+/// to drop. Returns `Err(EBADF)` for an unopened fd or one that isn't a
+/// regular file, `Ok(0)` otherwise.
+pub fn sys_fadvise64(fd: usize, _offset: usize, _len: usize, _advice: i32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    drop(inner);
+    if file.as_any().downcast_ref::<OSInode>().is_none() {
+        return Err(Errno::EBADF);
+    }
+    Ok(0)
+}
+
+/// Copy up to `len` bytes from `fd_in` to `fd_out` entirely inside the
+/// kernel, without a round trip through a user buffer. If `off_in`/`off_out`
+/// is non-null it's used (and updated) as the source/destination offset
+/// instead of the file's own read/write cursor, matching the Linux
+/// `copy_file_range(2)` contract. Returns the number of bytes copied, or
+/// `Err(EPERM)` if either fd isn't a regular file.
+pub fn sys_copy_file_range(
+    fd_in: usize,
+    off_in: *mut i64,
+    fd_out: usize,
+    off_out: *mut i64,
+    len: usize,
+    _flags: u32,
+) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let src = inner.fd_file(fd_in);
+    let dst = inner.fd_file(fd_out);
+    drop(inner);
+
+    let (src, dst) = match (src, dst) {
+        (Some(s), Some(d)) => (s, d),
+        _ => return Err(Errno::EPERM),
+    };
+    let (src, dst) = match (
+        src.as_any().downcast_ref::<OSInode>(),
+        dst.as_any().downcast_ref::<OSInode>(),
+    ) {
+        (Some(s), Some(d)) => (s, d),
+        _ => return Err(Errno::EPERM),
+    };
+
+    let mut src_pos = if off_in.is_null() {
+        src.offset()
+    } else {
+        *translated_refmut(token, off_in) as usize
+    };
+    let mut dst_pos = if off_out.is_null() {
+        dst.offset()
+    } else {
+        *translated_refmut(token, off_out) as usize
+    };
+
+    const CHUNK: usize = 512;
+    let mut buf = [0u8; CHUNK];
+    let mut copied = 0usize;
+    while copied < len {
+        let take = CHUNK.min(len - copied);
+        let n = src.inode().read_at(src_pos, &mut buf[..take]);
+        if n == 0 {
+            break;
+        }
+        let w = dst.inode().write_at(dst_pos, &buf[..n]);
+        src_pos += n;
+        dst_pos += w;
+        copied += w;
+        if w < n {
+            break;
+        }
+    }
+
+    if off_in.is_null() {
+        src.set_offset(src_pos);
+    } else {
+        *translated_refmut(token, off_in) = src_pos as i64;
+    }
+    if off_out.is_null() {
+        dst.set_offset(dst_pos);
+    } else {
+        *translated_refmut(token, off_out) = dst_pos as i64;
+    }
+    Ok(copied as isize)
+}
+
+#[repr(C)]
+pub struct IoVec {
+    pub base: *mut u8,
+    pub len: usize,
+}
+
+/// Shared implementation for `preadv`/`pwritev`: gather `iovcnt` `IoVec`s
+/// starting at `offset` in `fd`, reading or writing each in turn. Stops
+/// early on a short transfer, same convention as `OSInode::read`/`write`.
+fn readv_writev_at(
+    fd: usize,
+    iov_ptr: *const IoVec,
+    iovcnt: usize,
+    offset: usize,
+    write: bool,
+) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let file = match inner.fd_file(fd) {
+        Some(f) => f,
+        None => return Err(Errno::EPERM),
+    };
+    drop(inner);
+    let inode = match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => inode.inode(),
+        None => return Err(Errno::EPERM),
+    };
+
+    let mut pos = offset;
+    let mut total = 0usize;
+    for i in 0..iovcnt {
+        let iov = translated_refmut(token, unsafe { (iov_ptr as *mut IoVec).add(i) });
+        // Skip straight to the next iovec without ever translating `base`:
+        // a zero-length entry's `base` may be null or point at unmapped
+        // memory, same as a zero-length `sys_read`/`sys_write` buffer.
+        if iov.len == 0 {
+            continue;
+        }
+        let mut bufs = translated_byte_buffer(token, iov.base, iov.len);
+        let mut done_this_iov = 0usize;
+        for slice in bufs.iter_mut() {
+            let n = if write {
+                inode.write_at(pos, slice)
+            } else {
+                inode.read_at(pos, slice)
+            };
+            pos += n;
+            total += n;
+            done_this_iov += n;
+            if n < slice.len() {
+                break;
+            }
+        }
+        if done_this_iov < iov.len {
+            break;
+        }
+    }
+    Ok(total as isize)
+}
+
+/// Read from `fd` at `offset` into the `iovcnt` buffers described by `iov`,
+/// without disturbing the file's own read cursor.
+pub fn sys_preadv(fd: usize, iov: *const IoVec, iovcnt: usize, offset: usize) -> SyscallResult {
+    readv_writev_at(fd, iov, iovcnt, offset, false)
+}
+
+/// Write to `fd` at `offset` from the `iovcnt` buffers described by `iov`,
+/// without disturbing the file's own write cursor.
+pub fn sys_pwritev(fd: usize, iov: *const IoVec, iovcnt: usize, offset: usize) -> SyscallResult {
+    readv_writev_at(fd, iov, iovcnt, offset, true)
+}
+
+pub const POLLIN: i16 = 0x001;
+pub const POLLOUT: i16 = 0x004;
+
+#[repr(C)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+/// Check readiness of each `fds[i]` against `events` without blocking.
+/// Regular files (and anything that doesn't override `poll_read`/
+/// `poll_write`) are always reported ready; pipes report readiness based on
+/// their buffer state. Returns the number of fds with nonzero `revents`.
+pub fn sys_poll(fds_ptr: *mut PollFd, nfds: usize, _timeout_ms: isize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    drop(inner);
+
+    let mut ready_count = 0isize;
+    for i in 0..nfds {
+        let pollfd = translated_refmut(token, unsafe { fds_ptr.add(i) });
+        pollfd.revents = 0;
+        let process_inner = process.inner_exclusive_access();
+        let file = process_inner.fd_file(pollfd.fd as usize);
+        drop(process_inner);
+        if let Some(file) = file {
+            if pollfd.events & POLLIN != 0 && file.poll_read() {
+                pollfd.revents |= POLLIN;
+            }
+            if pollfd.events & POLLOUT != 0 && file.poll_write() {
+                pollfd.revents |= POLLOUT;
+            }
+            if pollfd.revents != 0 {
+                ready_count += 1;
+            }
+        }
+    }
+    Ok(ready_count)
+}
+
+/// Create a new epoll instance, returning its fd. `flags` is accepted (for
+/// `EPOLL_CLOEXEC`) and ignored, same as `sys_pipe`'s `flags`.
+pub fn sys_epoll_create1(_flags: i32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    Ok(inner.alloc_fd(Arc::new(Epoll::new())) as isize)
+}
+
+/// Add/modify/remove `fd`'s entry in the epoll instance `epfd`. Returns
+/// `Err(EPERM)` if `epfd` isn't an epoll fd.
+pub fn sys_epoll_ctl(epfd: usize, op: i32, fd: usize, event: *const EpollEvent) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let epoll_file = match inner.fd_file(epfd) {
+        Some(f) => f,
+        None => return Err(Errno::EPERM),
+    };
+    drop(inner);
+
+    let epoll = match epoll_file.as_any().downcast_ref::<Epoll>() {
+        Some(e) => e,
+        None => return Err(Errno::EPERM),
+    };
+    let event = if event.is_null() {
+        EpollEvent::default()
+    } else {
+        *translated_ref(token, event)
+    };
+    Ok(epoll.ctl(op, fd, event))
+}
+
+/// Block (up to `timeout_ms`, or forever if negative, or not at all if
+/// zero) until at least one watched fd is ready, reporting up to
+/// `maxevents` of them in `events`. Level-triggered: a still-ready fd is
+/// reported again on the next call. Returns the number of ready fds, or
+/// `Err(EPERM)` if `epfd` isn't an epoll fd.
+pub fn sys_epoll_wait(
+    epfd: usize,
+    events: *mut EpollEvent,
+    maxevents: usize,
+    timeout_ms: isize,
+) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let epoll_file = match inner.fd_file(epfd) {
+        Some(f) => f,
+        None => return Err(Errno::EPERM),
+    };
+    drop(inner);
+    let epoll = match epoll_file.as_any().downcast_ref::<Epoll>() {
+        Some(e) => e,
+        None => return Err(Errno::EPERM),
+    };
+
+    let deadline = if timeout_ms > 0 {
+        Some(get_time() + (timeout_ms as u64) * CLOCK_FREQ as u64 / 1000)
+    } else {
+        None
+    };
+
+    loop {
+        let watched = epoll.watched();
+        let inner = process.inner_exclusive_access();
+        let mut ready = Vec::new();
+        for (fd, ev) in watched.iter() {
+            if ready.len() >= maxevents {
+                break;
+            }
+            let file = match inner.fd_file(*fd) {
+                Some(f) => f,
+                None => continue,
+            };
+            let mut revents = 0u32;
+            if ev.events & EPOLLIN != 0 && file.poll_read() {
+                revents |= EPOLLIN;
+            }
+            if ev.events & EPOLLOUT != 0 && file.poll_write() {
+                revents |= EPOLLOUT;
+            }
+            if revents != 0 {
+                ready.push(EpollEvent {
+                    events: revents,
+                    data: ev.data,
+                });
+            }
+        }
+        drop(inner);
+
+        if !ready.is_empty() {
+            for (i, ev) in ready.iter().enumerate() {
+                *translated_refmut(token, unsafe { events.add(i) }) = *ev;
+            }
+            return Ok(ready.len() as isize);
+        }
+        if timeout_ms == 0 {
+            return Ok(0);
+        }
+        if let Some(deadline) = deadline {
+            if get_time() >= deadline {
+                return Ok(0);
+            }
+        }
+        suspend_current_and_run_next();
+    }
+}
+
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFLNK: u32 = 0o120000;
+
+/// Don't follow a trailing symlink in the path — the `lstat`-vs-`stat`
+/// distinction folded into `fstatat`'s `flags`, same as glibc does.
+pub const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+
+/// A small subset of `struct stat`: a type tag (`S_IFREG`/`S_IFDIR`/
+/// `S_IFLNK`) OR'd with the inode's permission bits in `mode`, plus `size`.
+/// No inode numbers or timestamps exist in this tree yet.
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub mode: u32,
+    pub size: u64,
+}
+
+/// `fstatat(dirfd, path, statbuf, flags)`. `dirfd` is accepted and ignored:
+/// `fs::inode`'s namespace is flat, so there's no relative-to-directory
+/// lookup for it to select, only the absolute `path`. Returns `Err(EPERM)`
+/// (really `ENOENT`, see [`Errno`]'s doc comment) if `path` (after
+/// following symlinks unless `AT_SYMLINK_NOFOLLOW` is set) doesn't resolve.
+pub fn sys_fstatat(_dirfd: i32, path: *const u8, statbuf: *mut Stat, flags: i32) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let root = inner.root.clone();
+    drop(inner);
+    let path = translated_str(token, path);
+    let path = resolve_chroot_path(&root, &path);
+
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    let inode = match resolve_inode(&path, follow) {
+        Some(inode) => inode,
+        None => return Err(Errno::EPERM),
+    };
+
+    let mode = (if inode.is_symlink() {
+        S_IFLNK
+    } else if inode.is_dir() {
+        S_IFDIR
+    } else {
+        S_IFREG
+    }) | inode.perm();
+    *translated_refmut(token, statbuf) = Stat {
+        mode,
+        size: inode.size() as u64,
+    };
+    Ok(0)
+}
+
+/// Read the target of the symlink at `path` into `buf`, up to `len` bytes.
+/// Like the real `readlinkat(2)`, the target is copied without a trailing
+/// NUL and truncated silently if it doesn't fit. Returns the number of
+/// bytes copied, or `Err(EPERM)` if `path` doesn't exist or isn't a
+/// symlink.
+pub fn sys_readlinkat(_dirfd: i32, path: *const u8, buf: *mut u8, len: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let path = translated_str(token, path);
+    let path = resolve_chroot_path(&inner.root, &path);
+
+    let inode = match resolve_inode(&path, false) {
+        Some(inode) => inode,
+        None => return Err(Errno::EPERM),
+    };
+    let target = match inode.symlink_target() {
+        Some(target) => target,
+        None => return Err(Errno::EPERM),
+    };
+
+    let bytes = target.as_bytes();
+    let n = bytes.len().min(len);
+    if !inner
+        .memory_set
+        .copy_to_user(VirtAddr::from(buf as usize), &bytes[..n])
+    {
+        return Err(Errno::EFAULT);
+    }
+    Ok(n as isize)
+}
+
+/// Set up a minimal io_uring-style ring pair for batched read/write
+/// submission: two anonymous, user-read-write mappings (sized for
+/// `entries` [`IoSqe`]/[`IoCqe`] slots respectively) are inserted right
+/// after the caller's highest existing mapping, same placement `sys_mmap`
+/// uses for an `addr == 0` request. Their base addresses are written to
+/// `sq_addr`/`cq_addr`; the caller fills submission entries directly into
+/// the first without any further syscall, then calls [`sys_iosubmit`] to
+/// process them. Returns the new ring's fd, or `Err(EFAULT)` if
+/// `sq_addr`/`cq_addr` don't resolve to writable user memory, or
+/// `Err(EINVAL)` if `entries` is `0`.
+pub fn sys_iosetup(entries: usize, sq_addr: *mut usize, cq_addr: *mut usize) -> SyscallResult {
+    if entries == 0 {
+        return Err(Errno::EINVAL);
+    }
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+
+    if try_translated_refmut(token, sq_addr).is_none()
+        || try_translated_refmut(token, cq_addr).is_none()
+    {
+        return Err(Errno::EFAULT);
+    }
+
+    let highest = inner
+        .memory_set
+        .areas
+        .iter()
+        .map(|a| a.vpn_range.get_end().0)
+        .max()
+        .unwrap_or(0);
+    let sq_start = VirtPageNum(highest);
+    let sq_bytes = entries * core::mem::size_of::<IoSqe>();
+    let sq_pages = VirtAddr::from(sq_bytes).ceil().ok_or(Errno::EINVAL)?.0;
+    let sq_end = VirtPageNum(sq_start.0 + sq_pages);
+    let cq_start = sq_end;
+    let cq_bytes = entries * core::mem::size_of::<IoCqe>();
+    let cq_pages = VirtAddr::from(cq_bytes).ceil().ok_or(Errno::EINVAL)?.0;
+    let cq_end = VirtPageNum(cq_start.0 + cq_pages);
+
+    let perm = MapPermission::U | MapPermission::R | MapPermission::W;
+    inner
+        .memory_set
+        .insert_mmap_area(sq_start.into(), sq_end.into(), perm);
+    inner
+        .memory_set
+        .insert_mmap_area(cq_start.into(), cq_end.into(), perm);
+
+    let sq_base: VirtAddr = sq_start.into();
+    let cq_base: VirtAddr = cq_start.into();
+    let ring = Arc::new(IoRing::new(sq_base, cq_base, entries));
+    let fd = inner.alloc_fd(ring) as isize;
+
+    *translated_refmut(token, sq_addr) = sq_base.0;
+    *translated_refmut(token, cq_addr) = cq_base.0;
+    Ok(fd)
+}
+
+/// Process the next `n` entries of `ring_fd`'s submission ring, each a
+/// plain read or write against a regular file already open in the
+/// caller's fd table (`IoSqe::fd`), posting one completion per entry to
+/// the completion ring. Entries are read starting at submission slot `0`
+/// every call — there's no shared submission read-cursor advanced between
+/// calls, since (unlike the completion side) nothing needs one yet: the
+/// caller already knows which slots it just wrote and passes `n` to match.
+/// Returns the number of completions posted, or `Err(EBADF)` if
+/// `ring_fd` isn't an io_uring fd, or `Err(EINVAL)` if `n` exceeds the
+/// ring's `entries`.
+///
+/// Everything runs synchronously before this returns — see [`IoRing`]'s
+/// doc comment for why that's the scope this starts at. An entry naming
+/// an unreadable/unwritable fd, or an opcode other than
+/// [`IORING_OP_READ`]/[`IORING_OP_WRITE`], posts a completion with
+/// `res == -EBADF` rather than aborting the whole batch, same as a real
+/// io_uring isolating one bad submission's failure to its own completion —
+/// that per-entry `res` is a value written into the completion ring, not
+/// this syscall's own return, so it stays a raw negated-errno `i64`
+/// rather than going through [`Errno`]/[`SyscallResult`] itself.
+pub fn sys_iosubmit(ring_fd: usize, n: usize) -> SyscallResult {
+    let task = current_task_or_esrch!(Err(Errno::ESRCH));
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let ring_file = match inner.fd_file(ring_fd) {
+        Some(f) => f,
+        None => return Err(Errno::EBADF),
+    };
+    let ring = match ring_file.as_any().downcast_ref::<IoRing>() {
+        Some(r) => r,
+        None => return Err(Errno::EBADF),
+    };
+    if n > ring.entries() {
+        return Err(Errno::EINVAL);
+    }
+    drop(inner);
+
+    for i in 0..n {
+        let sqe = *translated_ref(token, ring.sqe_addr(i) as *const IoSqe);
+        let inner = process.inner_exclusive_access();
+        let file = inner.fd_file(sqe.fd as usize);
+        drop(inner);
+
+        let res: i64 = match file.as_ref().and_then(|f| f.as_any().downcast_ref::<OSInode>()) {
+            None => -(Errno::EBADF as i64),
+            Some(os_inode) => {
+                let inode = os_inode.inode();
+                let len = sqe.len as usize;
+                let offset = sqe.offset as usize;
+                let mut data = translated_byte_buffer(token, sqe.buf as *mut u8, len);
+                match sqe.opcode {
+                    IORING_OP_READ if os_inode.readable() => {
+                        let mut total = 0usize;
+                        for slice in data.iter_mut() {
+                            let n = inode.read_at(offset + total, slice);
+                            total += n;
+                            if n < slice.len() {
+                                break;
+                            }
+                        }
+                        total as i64
+                    }
+                    IORING_OP_WRITE if os_inode.writable() => {
+                        let mut total = 0usize;
+                        for slice in data.iter() {
+                            let n = inode.write_at(offset + total, slice);
+                            total += n;
+                            if n < slice.len() {
+                                break;
+                            }
+                        }
+                        total as i64
+                    }
+                    _ => -(Errno::EBADF as i64),
+                }
+            }
+        };
+
+        let cqe = translated_refmut(token, ring.next_cqe_addr() as *mut IoCqe);
+        *cqe = IoCqe {
+            user_data: sqe.user_data,
+            res,
+        };
+    }
+    Ok(n as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mm::MemorySet;
+    use crate::task::ProcessControlBlock;
+
+    // `sys_readdir` (and every other fd-based syscall) looks up `fd` via
+    // `inner.fd_file(fd)`, returning `Errno::EBADF` when it's `None` — the
+    // exact check this covers, without needing a scheduled `current_task`
+    // to reach it through the syscall entry point itself.
+    #[test]
+    fn fd_file_is_none_for_an_unopened_fd() {
+        let process = ProcessControlBlock::new(MemorySet::new_bare());
+        let inner = process.inner_exclusive_access();
+        assert!(inner.fd_file(99).is_none());
+        // fd 0 (stdin) is open in every fresh process, so this isn't just
+        // vacuously true for every fd.
+        assert!(inner.fd_file(0).is_some());
+    }
+
+    #[test]
+    fn dirent_type_reports_dt_reg_and_dt_dir_from_the_target_inode() {
+        create_inode("/dirent_type_test_file", 0o644);
+        // Creates "/dirent_type_test_dir" itself as a directory inode, via
+        // the parent-creation side of `create_inode_with_parents`.
+        create_inode_with_parents("/dirent_type_test_dir/.keep", 0o644);
+
+        assert_eq!(dirent_type("/dirent_type_test_file"), DT_REG);
+        assert_eq!(dirent_type("/dirent_type_test_dir"), DT_DIR);
+    }
+
+    #[test]
+    fn dup_fd_shares_the_open_file_description_a_separate_open_does_not() {
+        let process = ProcessControlBlock::new(MemorySet::new_bare());
+        let mut inner = process.inner_exclusive_access();
+
+        create_inode("/dup_fd_test_file", 0o644);
+        let inode = open_inode("/dup_fd_test_file").unwrap();
+        let original = inner.alloc_fd(Arc::new(OSInode::new(true, true, inode.clone())));
+        let dupped = inner.dup_fd(original).unwrap();
+        let reopened = inner.alloc_fd(Arc::new(OSInode::new(true, true, inode)));
+
+        let as_osinode = |file: Arc<dyn File>| file.as_any().downcast_ref::<OSInode>().unwrap().offset();
+        inner
+            .fd_file(original)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<OSInode>()
+            .unwrap()
+            .set_offset(42);
+
+        assert_eq!(
+            as_osinode(inner.fd_file(dupped).unwrap()),
+            42,
+            "dup must share the same open file description, hence the same OSInode and cursor"
+        );
+        assert_eq!(
+            as_osinode(inner.fd_file(reopened).unwrap()),
+            0,
+            "a separate open gets its own OSInode, not the dup'd one's cursor"
+        );
+    }
+}
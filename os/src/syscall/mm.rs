@@ -0,0 +1,567 @@
+//! Memory-mapping syscalls: `mmap`, `munmap`, `mremap`.
+
+use super::current_task_or_esrch;
+use crate::config::PAGE_SIZE;
+use crate::fs::{File, OSInode};
+use crate::mm::{
+    translated_byte_buffer, vpn_ranges_overlap, MapArea, MapPermission, MapType, VirtAddr,
+    VirtPageNum, TRAP_CONTEXT,
+};
+
+pub const MREMAP_MAYMOVE: usize = 1;
+
+pub const PROT_NONE: usize = 0;
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+const PROT_VALID_MASK: usize = PROT_READ | PROT_WRITE | PROT_EXEC;
+
+pub const MAP_SHARED: usize = 1 << 0;
+pub const MAP_PRIVATE: usize = 1 << 1;
+pub const MAP_FIXED: usize = 1 << 4;
+pub const MAP_ANONYMOUS: usize = 1 << 5;
+/// Real Linux value (`0x0100`). Only meaningful for an anonymous mapping —
+/// a fault one page below its current bottom extends it downward instead
+/// of being fatal (the main user stack itself is fixed-size and never
+/// grows), up to a total size capped by the caller's `RLIMIT_STACK` (see
+/// `sys_mmap`'s doc comment for why that rlimit is what bounds it rather
+/// than a separate growsdown-specific limit). Ignored on a file-backed
+/// mapping, same as real `mmap(2)`.
+pub const MAP_GROWSDOWN: usize = 1 << 8;
+/// Real Linux value (`0x8000`): eagerly fault in every page of the mapping
+/// before `mmap` returns, instead of leaving it lazy. Only meaningful for
+/// the anonymous path — a file-backed mapping is built via `insert_area`,
+/// which already allocates and copies in every page's real frame up front
+/// (see `sys_mmap`'s doc comment), so there's nothing left for
+/// `MAP_POPULATE` to eagerly do there. Note `sys_mincore`'s residency check
+/// is PTE-validity, not "backed by its own real frame" — a lazy
+/// zero-frame-backed page already reads back as resident before
+/// `MAP_POPULATE` ever touches it, since the zero frame's PTE is valid too.
+/// What `MAP_POPULATE` actually buys here is a private frame per page up
+/// front instead of a shared one split off lazily on first write.
+pub const MAP_POPULATE: usize = 1 << 15;
+
+/// Map a region into the caller's address space, anonymous or file-backed.
+/// A file-backed mapping (`flags & MAP_ANONYMOUS == 0`) is initialized by
+/// reading `length` bytes from `fd` at `offset` (short reads are zero-padded,
+/// same as a real file mapping past EOF); `MAP_SHARED` additionally records
+/// the backing inode and offset in `file_mappings` so `sys_msync` can write
+/// the mapping's current contents back later. A `MAP_PRIVATE` file mapping
+/// is a one-time copy with nothing further tying it to the file — same as
+/// a real `mmap`, writes to it never reach the file and `msync` on it is a
+/// no-op. `fd` must name a plain file (an `OSInode`), not a pipe or epoll.
+///
+/// `MAP_GROWSDOWN` (anonymous mappings only) caps the area's eventual size
+/// at the calling process's `rlimit_stack` — the same rlimit the main
+/// stack is sized from at process start (see
+/// `ProcessControlBlockInner::rlimit_stack`'s doc comment) — rather than
+/// inventing a second, growsdown-specific limit: both are "how far can a
+/// stack-shaped region grow downward before it's treated as a runaway
+/// recursion/overflow instead of legitimate growth", so reusing the one
+/// rlimit keeps `sys_setrlimit(RLIMIT_STACK, ...)` meaningful for either.
+pub fn sys_mmap(addr: usize, length: usize, prot: usize, flags: usize, fd: i32, offset: usize) -> isize {
+    if prot & !PROT_VALID_MASK != 0 {
+        return -1;
+    }
+    if length == 0 {
+        return -1;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+
+    let file_inode = if flags & MAP_ANONYMOUS == 0 {
+        let file = match inner.fd_file(fd as usize) {
+            Some(f) => f,
+            None => return -1,
+        };
+        match file.as_any().downcast_ref::<OSInode>() {
+            Some(os_inode) => Some(os_inode.inode()),
+            None => return -1,
+        }
+    } else {
+        None
+    };
+
+    let start_vpn = if addr != 0 && flags & MAP_FIXED != 0 {
+        VirtAddr::from(addr).floor()
+    } else {
+        let highest = inner
+            .memory_set
+            .areas
+            .iter()
+            .map(|a| a.vpn_range.get_end().0)
+            .max()
+            .unwrap_or(0);
+        VirtPageNum(highest)
+    };
+    let length_pages = match VirtAddr::from(length).ceil() {
+        Some(vpn) => vpn.0,
+        None => return -1,
+    };
+    let end_vpn = VirtPageNum(start_vpn.0 + length_pages);
+
+    // The trap-context and trampoline pages sit at the very top of every
+    // address space (see `mm::memory_set::{TRAP_CONTEXT, TRAMPOLINE}`) and
+    // are never user-writable — `MAP_FIXED` letting a caller overlap them
+    // wouldn't just corrupt that mapping, it would let user code overwrite
+    // the kernel-entry trampoline or forge its own trap context, hijacking
+    // every subsequent trap into the kernel. Checked unconditionally (not
+    // just for `MAP_FIXED`) since even a "pick any address" request must
+    // never be satisfied by handing back an address in this range.
+    let trap_context_vpn = VirtAddr::from(TRAP_CONTEXT).floor();
+    if end_vpn > trap_context_vpn {
+        return -1;
+    }
+
+    // `MAP_FIXED` lets the caller name any address, including one that
+    // already backs an existing area — real `mmap(2)` would silently
+    // unmap the overlap first, but this tree has no partial-unmap/split
+    // path for an area, so the honest thing to do is reject the request
+    // rather than let it fall through into `insert_area`/`insert_mmap_area`,
+    // whose own overlap `assert!` (and `PageTable::map`'s "already mapped"
+    // one beneath it) would otherwise panic the whole kernel over an
+    // ordinary, unprivileged syscall argument.
+    if flags & MAP_FIXED != 0
+        && inner
+            .memory_set
+            .areas
+            .iter()
+            .any(|a| vpn_ranges_overlap(start_vpn, end_vpn, a.vpn_range.get_start(), a.vpn_range.get_end()))
+    {
+        return -1;
+    }
+
+    let mut perm = MapPermission::U;
+    if prot & PROT_READ != 0 {
+        perm |= MapPermission::R;
+    }
+    if prot & PROT_WRITE != 0 {
+        perm |= MapPermission::W;
+    }
+    if prot & PROT_EXEC != 0 {
+        perm |= MapPermission::X;
+    }
+
+    match &file_inode {
+        Some(inode) => {
+            let mut data = alloc::vec![0u8; length];
+            inode.read_at(offset, &mut data);
+            if inner
+                .memory_set
+                .insert_area(start_vpn.into(), end_vpn.into(), perm, Some(&data))
+                .is_err()
+            {
+                return -1;
+            }
+        }
+        None => {
+            if flags & MAP_SHARED != 0 {
+                // Real frames up front: the zero-frame/CoW pages of
+                // `insert_mmap_area` would split off privately on write.
+                if inner
+                    .memory_set
+                    .insert_area(start_vpn.into(), end_vpn.into(), perm, None)
+                    .is_err()
+                {
+                    return -1;
+                }
+            } else {
+                inner
+                    .memory_set
+                    .insert_mmap_area(start_vpn.into(), end_vpn.into(), perm);
+            }
+            if flags & MAP_POPULATE != 0 {
+                // Best-effort, same as the real flag: an exhausted
+                // allocator partway through just leaves the remaining
+                // pages lazy rather than failing the whole call.
+                for vpn in crate::mm::VPNRange::new(start_vpn, end_vpn) {
+                    let _ = inner.memory_set.prefault(vpn);
+                }
+            }
+            if flags & MAP_GROWSDOWN != 0 {
+                let max_pages = VirtAddr::from(inner.rlimit_stack).ceil().map(|vpn| vpn.0).unwrap_or(0);
+                let limit_vpn = VirtPageNum(end_vpn.0.saturating_sub(max_pages));
+                inner.memory_set.mark_growsdown(start_vpn, limit_vpn);
+            }
+        }
+    }
+
+    if flags & MAP_SHARED != 0 {
+        if let Some(area) = inner
+            .memory_set
+            .areas
+            .iter_mut()
+            .find(|a| a.vpn_range.get_start() == start_vpn)
+        {
+            area.shared = true;
+        }
+        if let Some(inode) = file_inode {
+            inner.file_mappings.insert(start_vpn, (inode, offset));
+        }
+    }
+    VirtAddr::from(start_vpn).0 as isize
+}
+
+pub const MS_ASYNC: usize = 1 << 0;
+pub const MS_INVALIDATE: usize = 1 << 1;
+pub const MS_SYNC: usize = 1 << 2;
+
+/// Write a `MAP_SHARED` file mapping's current page contents back to the
+/// file they're backed by, over `[addr, addr + length)`. `flags` is accepted
+/// for interface compatibility but every call behaves like `MS_SYNC`: there's
+/// no writeback daemon for `MS_ASYNC` to defer to, and no page cache separate
+/// from this mapping for `MS_INVALIDATE` to drop. Only pages that are
+/// actually mapped are written back (a never-faulted-in lazy page has
+/// nothing dirtier than the file it already reflects). Returns `-1` if
+/// `addr` doesn't fall inside a mapping recorded by `sys_mmap` as
+/// `MAP_SHARED` and file-backed — an anonymous or `MAP_PRIVATE` mapping has
+/// no entry in `file_mappings` and so nowhere to write back to.
+pub fn sys_msync(addr: usize, length: usize, _flags: usize) -> isize {
+    if length == 0 {
+        return 0;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = match VirtAddr::from(addr + length).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+
+    let area_start = match inner
+        .memory_set
+        .areas
+        .iter()
+        .find(|a| a.vpn_range.get_start().0 <= start_vpn.0 && start_vpn.0 < a.vpn_range.get_end().0)
+        .map(|a| a.vpn_range.get_start())
+    {
+        Some(start) => start,
+        None => return -1,
+    };
+    let (inode, file_offset) = match inner.file_mappings.get(&area_start) {
+        Some(entry) => entry.clone(),
+        None => return -1,
+    };
+
+    let mut vpn = start_vpn;
+    while vpn.0 < end_vpn.0 {
+        if let Some(pte) = inner.memory_set.page_table.translate(vpn) {
+            if pte.is_valid() {
+                let page_offset = file_offset + (vpn.0 - area_start.0) * PAGE_SIZE;
+                inode.write_at(page_offset, pte.ppn().get_bytes_array());
+            }
+        }
+        vpn.0 += 1;
+    }
+    0
+}
+
+/// Change the protection of the area starting at `addr` (same whole-area
+/// granularity as `mremap`). All of `PROT_READ`/`WRITE`/`EXEC` are taken
+/// literally, including re-adding a bit a previous call removed — there's
+/// no lazy "recompute on next fault" path, `set_area_permission` updates
+/// the live PTEs directly. Returns `-1` if no area starts exactly at `addr`.
+pub fn sys_mprotect(addr: usize, _length: usize, prot: usize) -> isize {
+    if prot & !PROT_VALID_MASK != 0 {
+        return -1;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let start_vpn = VirtAddr::from(addr).floor();
+
+    let mut perm = MapPermission::U;
+    if prot & PROT_READ != 0 {
+        perm |= MapPermission::R;
+    }
+    if prot & PROT_WRITE != 0 {
+        perm |= MapPermission::W;
+    }
+    if prot & PROT_EXEC != 0 {
+        perm |= MapPermission::X;
+    }
+
+    if inner.memory_set.set_area_permission(start_vpn, perm) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Pin `[addr, addr + length)` resident, failing with `-ENOMEM` (`-12`) if
+/// any page in the range isn't mapped. See `MemorySet::mlock`'s doc comment
+/// for why this is plumbing-only while this tree has no swap-out path for
+/// a lock to actually defend a page against.
+pub fn sys_mlock(addr: usize, length: usize) -> isize {
+    if length == 0 {
+        return 0;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = match VirtAddr::from(addr + length).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+    if inner.memory_set.mlock(start_vpn, end_vpn) {
+        0
+    } else {
+        -12
+    }
+}
+
+/// Undo [`sys_mlock`] over `[addr, addr + length)`. Never fails except for
+/// an `addr + length` so close to `usize::MAX` it can't be rounded up to a
+/// page boundary, which returns `-1` instead of overflowing.
+pub fn sys_munlock(addr: usize, length: usize) -> isize {
+    if length == 0 {
+        return 0;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = match VirtAddr::from(addr + length).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+    inner.memory_set.munlock(start_vpn, end_vpn);
+    0
+}
+
+/// Different from `MADV_DONTNEED`, which would drop a mapping's contents
+/// immediately: `MADV_FREE` only tells the kernel the app doesn't need
+/// the current contents *anymore*, so they're safe to drop the next time
+/// something actually reclaims memory. Until then the page keeps reading
+/// back its old data. See `MemorySet::madvise_free`'s doc comment for why
+/// that's a real distinction worth keeping apart from `DONTNEED` even
+/// though this tree has no other `MADV_*` implemented yet.
+pub const MADV_FREE: i32 = 8;
+
+/// Give the kernel a usage hint for `[addr, addr + length)`. Only
+/// `MADV_FREE` does anything here; any other (real or made-up) advice
+/// value is accepted and ignored, same as a real `madvise` silently
+/// no-ops an advice it doesn't act on (e.g. `MADV_RANDOM`/`MADV_WILLNEED`)
+/// rather than failing the call over it.
+pub fn sys_madvise(addr: usize, length: usize, advice: i32) -> isize {
+    if length == 0 {
+        return 0;
+    }
+    if advice != MADV_FREE {
+        return 0;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = match VirtAddr::from(addr + length).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+    inner.memory_set.madvise_free(start_vpn, end_vpn);
+    0
+}
+
+/// Report per-page residency for `[addr, addr + length)` into `vec`, one
+/// byte per page (`1` if the page has a valid PTE, `0` otherwise — a lazy
+/// page still on the shared zero frame reads as resident, see
+/// `MAP_POPULATE`'s doc comment). `length` need not
+/// be page-aligned; the final partial page still counts.
+pub fn sys_mincore(addr: usize, length: usize, vec: *mut u8) -> isize {
+    if length == 0 {
+        return 0;
+    }
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = match VirtAddr::from(addr + length).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+    let page_count = end_vpn.0 - start_vpn.0;
+
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let mut resident = alloc::vec::Vec::with_capacity(page_count);
+    for i in 0..page_count {
+        let vpn = VirtPageNum(start_vpn.0 + i);
+        let is_resident = inner
+            .memory_set
+            .page_table
+            .translate(vpn)
+            .is_some_and(|pte| pte.is_valid());
+        resident.push(is_resident as u8);
+    }
+    drop(inner);
+
+    let mut dst = translated_byte_buffer(token, vec, page_count);
+    let mut written = 0;
+    for slice in dst.iter_mut() {
+        slice.copy_from_slice(&resident[written..written + slice.len()]);
+        written += slice.len();
+    }
+    page_count as isize
+}
+
+/// Current and peak resident set size of the caller's address space, in
+/// pages, plus how many lazy/CoW faults it's taken. See
+/// `MemorySet::current_rss_pages`/`peak_rss_pages`/`minor_faults`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TaskStats {
+    pub rss_pages: usize,
+    pub peak_rss_pages: usize,
+    pub minor_faults: usize,
+}
+
+/// Not a real Linux syscall (the closest equivalent, `getrusage`, reports
+/// far more than this tree tracks): write the caller's current and peak RSS
+/// into `*out`.
+pub fn sys_task_stats(out: *mut TaskStats) -> isize {
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let stats = TaskStats {
+        rss_pages: inner.memory_set.current_rss_pages(),
+        peak_rss_pages: inner.memory_set.peak_rss_pages(),
+        minor_faults: inner.memory_set.minor_faults(),
+    };
+    drop(inner);
+    *crate::mm::translated_refmut(token, out) = stats;
+    0
+}
+
+/// Resize an existing mapping created with `mmap`.
+///
+/// Grows in place when the virtual range immediately after the mapping is
+/// free; otherwise, if `MREMAP_MAYMOVE` is set, allocates a fresh region and
+/// moves the backing frames there (no data copy needed, since the same
+/// frames are simply remapped). Returns the resulting base address, or
+/// `-ENOMEM` if growing in place isn't possible and the caller didn't allow
+/// a move.
+pub fn sys_mremap(
+    old_addr: usize,
+    old_len: usize,
+    new_len: usize,
+    flags: usize,
+    new_addr: usize,
+) -> isize {
+    if old_addr % PAGE_SIZE != 0 || new_len == 0 {
+        return -1;
+    }
+    let task = current_task_or_esrch!();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let memory_set = &mut inner.memory_set;
+
+    let old_start_vpn: VirtPageNum = VirtAddr::from(old_addr).floor();
+    let old_end_vpn: VirtPageNum = match VirtAddr::from(old_addr + old_len).ceil() {
+        Some(vpn) => vpn,
+        None => return -1,
+    };
+    let new_page_count = match VirtAddr::from(new_len).ceil() {
+        Some(vpn) => vpn.0,
+        None => return -1,
+    };
+
+    let area_idx = match memory_set
+        .areas
+        .iter()
+        .position(|a| a.vpn_range.get_start() == old_start_vpn)
+    {
+        Some(idx) => idx,
+        None => return -1,
+    };
+
+    let (map_type, map_perm) = {
+        let area = &memory_set.areas[area_idx];
+        (area.map_type, area.map_perm)
+    };
+
+    let new_end_vpn = VirtPageNum(old_start_vpn.0 + new_page_count);
+    let grows_in_place = new_end_vpn.0 <= old_end_vpn.0
+        || !memory_set.areas.iter().enumerate().any(|(i, a)| {
+            i != area_idx
+                && a.vpn_range.get_start().0 < new_end_vpn.0
+                && a.vpn_range.get_end().0 > old_end_vpn.0
+        });
+
+    if grows_in_place {
+        if new_end_vpn.0 > old_end_vpn.0 {
+            let mut vpn = old_end_vpn;
+            while vpn.0 < new_end_vpn.0 {
+                memory_set.areas[area_idx].map_one(&mut memory_set.page_table, vpn);
+                vpn.0 += 1;
+            }
+            memory_set.note_rss_growth();
+        } else if new_end_vpn.0 < old_end_vpn.0 {
+            let mut vpn = new_end_vpn;
+            while vpn.0 < old_end_vpn.0 {
+                memory_set.areas[area_idx].unmap_one(&mut memory_set.page_table, vpn);
+                vpn.0 += 1;
+            }
+        }
+        memory_set.areas[area_idx].vpn_range =
+            crate::mm::VPNRange::new(old_start_vpn, new_end_vpn);
+        return VirtAddr::from(old_start_vpn).0 as isize;
+    }
+
+    if flags & MREMAP_MAYMOVE == 0 {
+        return -12;
+    }
+
+    // Move: pick the caller-provided address if given, else bump past the
+    // highest mapped area, then relocate the frames without copying.
+    let new_base_vpn = if new_addr != 0 {
+        VirtAddr::from(new_addr).floor()
+    } else {
+        let highest = memory_set
+            .areas
+            .iter()
+            .map(|a| a.vpn_range.get_end().0)
+            .max()
+            .unwrap_or(0);
+        VirtPageNum(highest)
+    };
+
+    let mut old_area = memory_set.areas.remove(area_idx);
+    let mut new_area = MapArea::new(
+        VirtAddr::from(new_base_vpn),
+        VirtAddr::from(VirtPageNum(new_base_vpn.0 + new_page_count)),
+        map_type,
+        map_perm,
+    );
+    let pte_flags = crate::mm::PTEFlags::from_bits(map_perm.bits()).unwrap();
+
+    // Move each previously-backed page to its new vpn, reusing the frame
+    // (no data copy): unmap the old pte, map the new one to the same ppn.
+    let mut old_vpn = old_area.vpn_range.get_start();
+    let mut new_vpn = new_base_vpn;
+    while old_vpn.0 < old_area.vpn_range.get_end().0 {
+        if let Some(frame) = old_area.data_frames.remove(&old_vpn) {
+            memory_set.page_table.unmap(old_vpn);
+            memory_set.page_table.map(new_vpn, frame.ppn, pte_flags);
+            new_area.data_frames.insert(new_vpn, frame);
+        }
+        old_vpn.0 += 1;
+        new_vpn.0 += 1;
+    }
+    // The newly requested tail beyond the old length needs real frames the
+    // same way the grow-in-place branch above eagerly maps its tail: a page
+    // with no PTE at all isn't a valid lazy mapping like `insert_mmap_area`'s
+    // zero-frame pages are, it's just unbacked, so a fault into it would hit
+    // `handle_page_fault`'s `None` case and be fatal forever, even though
+    // `access_ok` already reports the area as present.
+    let new_end_vpn = VirtPageNum(new_base_vpn.0 + new_page_count);
+    while new_vpn.0 < new_end_vpn.0 {
+        new_area.map_one(&mut memory_set.page_table, new_vpn);
+        new_vpn.0 += 1;
+    }
+    memory_set.note_rss_growth();
+    memory_set.areas.push(new_area);
+    VirtAddr::from(new_base_vpn).0 as isize
+}
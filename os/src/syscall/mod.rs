@@ -0,0 +1,297 @@
+//! Syscall dispatch, grouped by subsystem the way the rest of the kernel
+//! is organized (`mm`, `fs`, `process`, ...).
+
+mod debug;
+mod error;
+mod exec;
+mod fs;
+mod mm;
+mod process;
+mod time;
+
+pub use error::{into_isize, Errno, SyscallResult};
+
+/// Linux's "no such process" — what [`current_task_or_esrch`] returns in
+/// place of a current task, for the syscall modules that haven't migrated
+/// onto [`SyscallResult`]/[`Errno::ESRCH`] yet.
+pub const ESRCH: isize = -3;
+
+/// Every syscall that needs "the calling task" used to reach for
+/// `current_task().unwrap()`. By the time a syscall handler runs there
+/// should always be one — this kernel has no background threads or
+/// interrupt-context callers that could reach a syscall path with no task
+/// scheduled — so a `None` here would mean a genuine scheduler bug rather
+/// than an expected race. But panicking over it takes the whole kernel
+/// down for what a real OS reports as one failed call (`ESRCH`), so every
+/// `sys_*` that used to unwrap now bails out through this macro instead.
+/// Defaults to returning the bare [`ESRCH`] constant for modules still on
+/// plain `isize`; a migrated module (see `syscall::fs`) passes an explicit
+/// `Err(Errno::ESRCH)` to return instead.
+macro_rules! current_task_or_esrch {
+    () => {
+        current_task_or_esrch!(crate::syscall::ESRCH)
+    };
+    ($on_none:expr) => {
+        match crate::task::current_task() {
+            Some(task) => task,
+            None => return $on_none,
+        }
+    };
+}
+pub(crate) use current_task_or_esrch;
+
+pub use debug::{sys_fsck, sys_meminfo, sys_mmu_walk, PageWalkLevel, SystemMemoryInfo};
+pub use exec::exec_replace_memory_set;
+pub use fs::{
+    sys_chdir, sys_chroot, sys_close, sys_copy_file_range, sys_dup, sys_dup2, sys_epoll_create1,
+    sys_epoll_ctl, sys_epoll_wait, sys_fadvise64, sys_fallocate, sys_flock, sys_fstatat, sys_getcwd,
+    sys_getdents64, sys_iosetup, sys_iosubmit, sys_open, sys_pipe, sys_poll, sys_preadv,
+    sys_pwritev, sys_read, sys_readdir, sys_readlinkat, sys_splice, sys_write, IoVec, Stat,
+    DT_DIR, DT_FIFO, DT_LNK, DT_REG, DT_UNKNOWN, LOCK_EX, LOCK_NB, LOCK_SH, LOCK_UN,
+};
+pub use mm::{
+    sys_madvise, sys_mincore, sys_mlock, sys_mmap, sys_mprotect, sys_mremap, sys_msync,
+    sys_munlock, sys_task_stats, TaskStats, MREMAP_MAYMOVE,
+};
+pub use process::{
+    sys_exit_group, sys_getgid, sys_getpgid, sys_getrlimit, sys_getuid, sys_kill, sys_membarrier,
+    sys_pause, sys_process_vm_readv, sys_process_vm_writev, sys_setgid, sys_setpgid, sys_setsid,
+    sys_setrlimit, sys_setuid, sys_sigaction, sys_sigprocmask, sys_tgkill, sys_umask, sys_vfork,
+    RLimit,
+};
+pub use time::{sys_clock_gettime, sys_gettimeofday, sys_nanosleep, Timeval};
+
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_SPLICE: usize = 76;
+const SYSCALL_COPY_FILE_RANGE: usize = 285;
+const SYSCALL_PREADV: usize = 69;
+const SYSCALL_PWRITEV: usize = 70;
+/// riscv64 Linux has no plain `pipe`, only `pipe2`; `flags` is accepted and
+/// ignored since this kernel's pipes don't support `O_NONBLOCK`/`O_CLOEXEC`.
+const SYSCALL_PIPE2: usize = 59;
+const SYSCALL_EXIT_GROUP: usize = 94;
+const SYSCALL_SETUID: usize = 146;
+const SYSCALL_SETGID: usize = 144;
+const SYSCALL_GETUID: usize = 174;
+const SYSCALL_GETGID: usize = 176;
+const SYSCALL_POLL: usize = 73;
+const SYSCALL_MREMAP: usize = 216;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_MSYNC: usize = 227;
+const SYSCALL_FALLOCATE: usize = 47;
+const SYSCALL_FADVISE64: usize = 223;
+const SYSCALL_UMASK: usize = 166;
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_FLOCK: usize = 32;
+const SYSCALL_GETDENTS64: usize = 61;
+/// riscv64 Linux has no plain `dup2`, only `dup3` (an extra `flags` arg for
+/// `O_CLOEXEC`); that arg is accepted and ignored here, same as `sys_pipe`
+/// ignoring `pipe2`'s flags.
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_CHROOT: usize = 51;
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_MINCORE: usize = 232;
+const SYSCALL_MLOCK: usize = 228;
+const SYSCALL_MUNLOCK: usize = 229;
+const SYSCALL_MADVISE: usize = 233;
+const SYSCALL_PAUSE: usize = 34;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_TGKILL: usize = 131;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_GETTIMEOFDAY: usize = 169;
+const SYSCALL_NANOSLEEP: usize = 101;
+/// Not a real Linux syscall number: a kernel-debugging helper that exposes
+/// the Sv39 page-table walk for a virtual address to userspace.
+const SYSCALL_MMU_WALK: usize = 1001;
+/// Not a real Linux syscall number: a kernel-specific convenience on top of
+/// `getdents64` for reading one directory entry at a time.
+const SYSCALL_READDIR: usize = 1000;
+/// Not a real Linux syscall number: reports this tree's minimal RSS/peak-RSS
+/// stats (see `TaskStats`), the part of `getrusage` this kernel actually
+/// tracks.
+const SYSCALL_TASK_STATS: usize = 1002;
+/// Not a real Linux syscall number: riscv64 only has `prlimit64`, which
+/// covers arbitrary pids and both current/max limits. This kernel only
+/// ever targets the caller, so `setrlimit`/`getrlimit` live at their own
+/// kernel-internal numbers instead of emulating the wider `prlimit64`
+/// contract.
+const SYSCALL_SETRLIMIT: usize = 1003;
+const SYSCALL_GETRLIMIT: usize = 1004;
+const SYSCALL_EPOLL_CREATE1: usize = 20;
+const SYSCALL_EPOLL_CTL: usize = 21;
+/// riscv64 Linux only has `epoll_pwait` (an extra trailing sigmask arg over
+/// `epoll_wait`); that arg is accepted and ignored here, same as `sys_pipe`
+/// ignoring `pipe2`'s flags.
+const SYSCALL_EPOLL_PWAIT: usize = 22;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
+/// Not a real Linux syscall number: a kernel-internal fsck-style consistency
+/// check over `fs::inode`'s namespace, see [`sys_fsck`].
+const SYSCALL_FSCK: usize = 1005;
+/// Not a real Linux syscall number: a kernel-debugging helper that sums
+/// resident pages across every live process, see [`sys_meminfo`].
+const SYSCALL_MEMINFO: usize = 1006;
+const SYSCALL_PROCESS_VM_READV: usize = 270;
+const SYSCALL_PROCESS_VM_WRITEV: usize = 271;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_SETSID: usize = 157;
+/// Not a real Linux syscall number: riscv64 has no raw `vfork` syscall at
+/// all (glibc synthesizes it via `clone(CLONE_VM|CLONE_VFORK|SIGCHLD,
+/// ...)`), and this tree has no `clone` syscall to build it on top of
+/// either. See [`sys_vfork`]'s doc comment for why it's a stub.
+const SYSCALL_VFORK: usize = 1007;
+const SYSCALL_MEMBARRIER: usize = 283;
+/// Not a real Linux syscall number: real `io_uring_setup` takes a single
+/// `struct io_uring_params` out-param and a different submission/
+/// completion entry layout than [`sys_iosetup`]'s two raw address
+/// out-params and [`crate::fs::IoSqe`]/[`crate::fs::IoCqe`], so reusing
+/// its number (`425`) would be misleading about ABI compatibility that
+/// doesn't exist.
+const SYSCALL_IOSETUP: usize = 1008;
+/// Not a real Linux syscall number, for the same reason as
+/// [`SYSCALL_IOSETUP`]: real `io_uring_enter` takes flags and a signal
+/// mask `sys_iosubmit` doesn't.
+const SYSCALL_IOSUBMIT: usize = 1009;
+
+pub fn syscall(id: usize, args: [usize; 6]) -> isize {
+    match id {
+        SYSCALL_READ => into_isize(sys_read(args[0], args[1] as *mut u8, args[2])),
+        SYSCALL_WRITE => into_isize(sys_write(args[0], args[1] as *const u8, args[2])),
+        SYSCALL_OPEN => into_isize(sys_open(args[0] as *const u8, args[1] as u32, args[2] as u32)),
+        SYSCALL_UMASK => sys_umask(args[0] as u32),
+        SYSCALL_CLOSE => into_isize(sys_close(args[0])),
+        SYSCALL_SPLICE => into_isize(sys_splice(args[0], args[2], args[4])),
+        SYSCALL_COPY_FILE_RANGE => into_isize(sys_copy_file_range(
+            args[0],
+            args[1] as *mut i64,
+            args[2],
+            args[3] as *mut i64,
+            args[4],
+            args[5] as u32,
+        )),
+        SYSCALL_SETUID => sys_setuid(args[0] as u32),
+        SYSCALL_SETGID => sys_setgid(args[0] as u32),
+        SYSCALL_GETUID => sys_getuid(),
+        SYSCALL_GETGID => sys_getgid(),
+        SYSCALL_EXIT_GROUP => sys_exit_group(args[0] as i32),
+        SYSCALL_POLL => into_isize(sys_poll(
+            args[0] as *mut fs::PollFd,
+            args[1],
+            args[2] as isize,
+        )),
+        SYSCALL_READDIR => into_isize(sys_readdir(args[0], args[1] as *mut u8, args[2])),
+        SYSCALL_CLOCK_GETTIME => {
+            sys_clock_gettime(args[0], args[1] as *mut crate::timer::TimeSpec)
+        }
+        SYSCALL_GETTIMEOFDAY => sys_gettimeofday(args[0] as *mut Timeval, args[1] as *mut u8),
+        SYSCALL_NANOSLEEP => sys_nanosleep(
+            args[0] as *const crate::timer::TimeSpec,
+            args[1] as *mut crate::timer::TimeSpec,
+        ),
+        SYSCALL_MMAP => sys_mmap(
+            args[0],
+            args[1],
+            args[2],
+            args[3],
+            args[4] as i32,
+            args[5],
+        ),
+        SYSCALL_MREMAP => sys_mremap(args[0], args[1], args[2], args[3], args[4]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYSCALL_MSYNC => sys_msync(args[0], args[1], args[2]),
+        SYSCALL_DUP => into_isize(sys_dup(args[0])),
+        SYSCALL_DUP3 => into_isize(sys_dup2(args[0], args[1])),
+        SYSCALL_CHDIR => into_isize(sys_chdir(args[0] as *const u8)),
+        SYSCALL_CHROOT => into_isize(sys_chroot(args[0] as *const u8)),
+        SYSCALL_GETCWD => into_isize(sys_getcwd(args[0] as *mut u8, args[1])),
+        SYSCALL_SETRLIMIT => sys_setrlimit(args[0] as i32, args[1] as *const RLimit),
+        SYSCALL_GETRLIMIT => sys_getrlimit(args[0] as i32, args[1] as *mut RLimit),
+        SYSCALL_FALLOCATE => into_isize(sys_fallocate(args[0], args[1] as i32, args[2], args[3])),
+        SYSCALL_FADVISE64 => into_isize(sys_fadvise64(args[0], args[1], args[2], args[3] as i32)),
+        SYSCALL_MINCORE => sys_mincore(args[0], args[1], args[2] as *mut u8),
+        SYSCALL_MLOCK => sys_mlock(args[0], args[1]),
+        SYSCALL_MUNLOCK => sys_munlock(args[0], args[1]),
+        SYSCALL_MADVISE => sys_madvise(args[0], args[1], args[2] as i32),
+        SYSCALL_PAUSE => sys_pause(),
+        SYSCALL_FLOCK => into_isize(sys_flock(args[0], args[1] as i32)),
+        SYSCALL_GETDENTS64 => into_isize(sys_getdents64(args[0], args[1] as *mut u8, args[2])),
+        SYSCALL_KILL => sys_kill(args[0] as isize, args[1] as i32),
+        SYSCALL_TGKILL => sys_tgkill(args[0], args[1], args[2] as i32),
+        SYSCALL_SIGACTION => sys_sigaction(
+            args[0] as i32,
+            args[1] as *const crate::task::SignalAction,
+            args[2] as *mut crate::task::SignalAction,
+        ),
+        SYSCALL_SIGPROCMASK => sys_sigprocmask(
+            args[0] as i32,
+            args[1] as *const u32,
+            args[2] as *mut u32,
+        ),
+        SYSCALL_MMU_WALK => sys_mmu_walk(args[0], args[1] as *mut PageWalkLevel),
+        SYSCALL_PREADV => into_isize(sys_preadv(args[0], args[1] as *const IoVec, args[2], args[3])),
+        SYSCALL_PWRITEV => into_isize(sys_pwritev(args[0], args[1] as *const IoVec, args[2], args[3])),
+        SYSCALL_PIPE2 => into_isize(sys_pipe(args[0] as *mut i32)),
+        SYSCALL_TASK_STATS => sys_task_stats(args[0] as *mut TaskStats),
+        SYSCALL_EPOLL_CREATE1 => into_isize(sys_epoll_create1(args[0] as i32)),
+        SYSCALL_EPOLL_CTL => into_isize(sys_epoll_ctl(
+            args[0],
+            args[1] as i32,
+            args[2],
+            args[3] as *const crate::fs::EpollEvent,
+        )),
+        SYSCALL_EPOLL_PWAIT => into_isize(sys_epoll_wait(
+            args[0],
+            args[1] as *mut crate::fs::EpollEvent,
+            args[2],
+            args[3] as isize,
+        )),
+        SYSCALL_READLINKAT => into_isize(sys_readlinkat(
+            args[0] as i32,
+            args[1] as *const u8,
+            args[2] as *mut u8,
+            args[3],
+        )),
+        SYSCALL_FSTATAT => into_isize(sys_fstatat(
+            args[0] as i32,
+            args[1] as *const u8,
+            args[2] as *mut Stat,
+            args[3] as i32,
+        )),
+        SYSCALL_PROCESS_VM_READV => sys_process_vm_readv(
+            args[0],
+            args[1] as *const IoVec,
+            args[2],
+            args[3] as *const IoVec,
+            args[4],
+            args[5],
+        ),
+        SYSCALL_PROCESS_VM_WRITEV => sys_process_vm_writev(
+            args[0],
+            args[1] as *const IoVec,
+            args[2],
+            args[3] as *const IoVec,
+            args[4],
+            args[5],
+        ),
+        SYSCALL_SETPGID => sys_setpgid(args[0], args[1]),
+        SYSCALL_GETPGID => sys_getpgid(args[0]),
+        SYSCALL_SETSID => sys_setsid(),
+        SYSCALL_VFORK => sys_vfork(),
+        SYSCALL_MEMBARRIER => sys_membarrier(args[0] as i32, args[1] as i32),
+        SYSCALL_IOSETUP => into_isize(sys_iosetup(args[0], args[1] as *mut usize, args[2] as *mut usize)),
+        SYSCALL_IOSUBMIT => into_isize(sys_iosubmit(args[0], args[1])),
+        SYSCALL_FSCK => sys_fsck(),
+        SYSCALL_MEMINFO => sys_meminfo(args[0] as *mut SystemMemoryInfo),
+        _ => {
+            panic!("Unsupported syscall_id: {}", id);
+        }
+    }
+}
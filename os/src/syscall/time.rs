@@ -0,0 +1,71 @@
+//! Time-related syscalls.
+
+use crate::mm::translated_refmut;
+use crate::task::{current_user_token, suspend_current_and_run_next};
+use crate::timer::{get_time, get_time_spec, TimeSpec};
+use crate::config::CLOCK_FREQ;
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+/// Both clock ids return the same monotonic-since-boot reading in this
+/// kernel: there's no wall-clock source (RTC) wired up to make
+/// `CLOCK_REALTIME` meaningfully different yet.
+pub fn sys_clock_gettime(clock_id: usize, ts_ptr: *mut TimeSpec) -> isize {
+    if clock_id != CLOCK_REALTIME && clock_id != CLOCK_MONOTONIC {
+        return -1;
+    }
+    let ts = translated_refmut(current_user_token(), ts_ptr);
+    *ts = get_time_spec();
+    0
+}
+
+/// `struct timeval`: seconds plus microseconds, the pre-`clock_gettime`
+/// representation a lot of ported programs still call `gettimeofday` for.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Timeval {
+    pub sec: u64,
+    pub usec: u64,
+}
+
+/// Fill `*tv` from the same clock source as `sys_clock_gettime`, truncating
+/// its nanosecond resolution down to microseconds. `tz` is accepted for
+/// interface compatibility and ignored — like real Linux, this kernel has
+/// never had a concept of timezones to report through it.
+pub fn sys_gettimeofday(tv: *mut Timeval, _tz: *mut u8) -> isize {
+    let ts = get_time_spec();
+    let tv = translated_refmut(current_user_token(), tv);
+    *tv = Timeval {
+        sec: ts.sec,
+        usec: ts.nsec / 1_000,
+    };
+    0
+}
+
+/// Suspend the calling task until at least `req` has elapsed.
+///
+/// There's no ready-queue scheduler in this tree yet, so "suspend" can't
+/// park the task and switch to another one; instead this busy-waits on the
+/// `time` CSR, yielding the hart via [`suspend_current_and_run_next`] each
+/// iteration so the eventual scheduler only has to make that call actually
+/// switch tasks for `sys_nanosleep` to behave correctly without changes
+/// here. `rem` is always left zeroed since the wait is never interrupted
+/// early by a signal in this kernel.
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
+    let token = current_user_token();
+    let req = *crate::mm::translated_ref(token, req);
+    let wake_ticks = get_time()
+        + req.sec * CLOCK_FREQ as u64
+        + (req.nsec * CLOCK_FREQ as u64 / NSEC_PER_SEC);
+    while get_time() < wake_ticks {
+        suspend_current_and_run_next();
+    }
+    if !rem.is_null() {
+        let rem = translated_refmut(token, rem);
+        *rem = TimeSpec::default();
+    }
+    0
+}
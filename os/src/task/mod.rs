@@ -0,0 +1,14 @@
+//! Task (thread) and process scheduling state.
+
+mod context;
+mod pid;
+mod process;
+mod processor;
+mod signal;
+mod task;
+
+pub use pid::{pid_alloc, PidHandle};
+pub use process::{all_live_processes, process_by_pid, ProcessControlBlock, ProcessControlBlockInner};
+pub use processor::{current_task, current_user_token, suspend_current_and_run_next, PROCESSOR};
+pub use signal::{SignalAction, SignalFlags, MAX_SIG};
+pub use task::{TaskControlBlock, TaskControlBlockInner, TaskStatus};
@@ -0,0 +1,265 @@
+//! The process control block: one address space, shared by every
+//! `TaskControlBlock` (thread) that belongs to it.
+
+use super::pid::{pid_alloc, PidHandle};
+use super::signal::{SignalAction, MAX_SIG};
+use super::task::TaskControlBlock;
+use crate::fs::{File, FdFlags, Inode, OpenFileDescription, Stderr, Stdin, Stdout};
+use crate::mm::{MemorySet, VirtPageNum};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+pub struct ProcessControlBlock {
+    pub pid: PidHandle,
+    inner: UPSafeCell<ProcessControlBlockInner>,
+}
+
+pub struct ProcessControlBlockInner {
+    pub memory_set: MemorySet,
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    pub exit_code: i32,
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    /// Each slot pairs the shared open file description with the
+    /// per-fd-number flags that `dup`/`dup2` must NOT carry over to the new
+    /// fd — see `OpenFileDescription`/`FdFlags`.
+    pub fd_table: Vec<Option<(Arc<OpenFileDescription>, FdFlags)>>,
+    pub uid: u32,
+    pub gid: u32,
+    /// Registered `sigaction(2)` handler per signal number, indexed
+    /// `[0..=MAX_SIG]` (index `0` is unused — signal numbers start at 1).
+    pub signal_actions: Vec<SignalAction>,
+    /// Soft `RLIMIT_STACK` in bytes, inherited across `fork`. Read back by
+    /// `sys_getrlimit`, and used by `sys_mmap` to cap how far a
+    /// `MAP_GROWSDOWN` area may grow (checked on each growth fault by
+    /// `MemorySet::handle_page_fault`). The main user stack is fixed-size
+    /// and never grows, so its size is not checked against this.
+    pub rlimit_stack: usize,
+    /// The process's current working directory, stored as the full path
+    /// used to key it in `fs::inode`'s flat `INODE_TABLE`. `fs::inode` has
+    /// no directory hierarchy with parent pointers to walk — every inode is
+    /// already named by its full path — so unlike a real VFS there's
+    /// nothing for `getcwd` to reconstruct and so nothing for an
+    /// inode-id-to-path cache to speed up; the full path *is* the cache.
+    pub cwd: String,
+    /// The real path this process's absolute paths are confined under, per
+    /// `sys_chroot`. Every absolute-path syscall resolves its `path`
+    /// argument against this via `resolve_chroot_path` before touching
+    /// `fs::inode`'s namespace, so a `..` can't walk back out past it.
+    /// Defaults to `/`, i.e. unconfined.
+    pub root: String,
+    /// File-backed `MAP_SHARED` regions created by `sys_mmap`, keyed by the
+    /// mapping's start vpn, recording the inode and file offset its first
+    /// page corresponds to. Only `MAP_SHARED` file mappings ever have an
+    /// entry here — a private file mapping's pages are a one-time copy with
+    /// nowhere to write back to, and an anonymous mapping isn't file-backed
+    /// at all — so this is exactly what `sys_msync` needs and nothing a
+    /// private or anonymous area would ever look up.
+    pub file_mappings: BTreeMap<VirtPageNum, (Arc<Inode>, usize)>,
+    /// File-creation mask set by `sys_umask`: `sys_open`'s `O_CREAT` path
+    /// strips these bits from its `mode` argument before applying it to a
+    /// newly created inode's permission bits. Defaults to the traditional
+    /// Unix `0o022`. Naturally persists across `exec` (this process's
+    /// `ProcessControlBlockInner` is reused, not replaced); whether it
+    /// should be inherited across `fork` isn't yet observable — this tree
+    /// has no `fork`/`clone` syscall wired up to test against.
+    pub umask: u32,
+    /// Process group id, for job control (`kill(-pgid, sig)`, a shell's
+    /// terminal-control handoff). Defaults to this process's own pid — a
+    /// fresh process is its own group leader until `sys_setpgid` moves it
+    /// into another group. See `sys_setpgid`/`sys_getpgid`.
+    pub pgid: usize,
+    /// Session id, for terminal job control (a session is the set of
+    /// process groups sharing a controlling terminal). Defaults to this
+    /// process's own pid, same as `pgid` — a fresh process is its own
+    /// session leader until it's moved into another session, which this
+    /// tree has no syscall for since there's no `fork`/`exec` pair that
+    /// would produce a child to move. `sys_setsid` is the only way to
+    /// change it, and only to make the caller a session (and group)
+    /// leader of a brand new session.
+    pub sid: usize,
+}
+
+/// The fd table every new process starts with: fd 0/1/2 bound to
+/// stdin/stdout/stderr, POSIX's standing guarantee that a program can read
+/// and write those three without an explicit `open`. Centralized here
+/// rather than left to whatever eventually creates the first process, so
+/// [`ProcessControlBlock::new`] is the single place that guarantee holds —
+/// an `exec` reuses this same `ProcessControlBlockInner` rather than
+/// building a new one (see `umask`'s doc comment above), so the standard
+/// fds survive it automatically without a separate "carry them over" step;
+/// a real `fork`/`clone`, once this tree has one, would do the same by
+/// cloning this `fd_table` wholesale along with the rest of the inner
+/// state it copies.
+fn initial_fd_table() -> Vec<Option<(Arc<OpenFileDescription>, FdFlags)>> {
+    alloc::vec![
+        Some((
+            OpenFileDescription::new(Arc::new(Stdin) as Arc<dyn File>),
+            FdFlags::empty()
+        )),
+        Some((
+            OpenFileDescription::new(Arc::new(Stdout) as Arc<dyn File>),
+            FdFlags::empty()
+        )),
+        Some((
+            OpenFileDescription::new(Arc::new(Stderr) as Arc<dyn File>),
+            FdFlags::empty()
+        )),
+    ]
+}
+
+impl ProcessControlBlockInner {
+    /// Number of threads that have not yet exited.
+    pub fn thread_count(&self) -> usize {
+        self.tasks.iter().filter(|t| t.is_some()).count()
+    }
+
+    /// Install `file` at the lowest unused fd, POSIX's guarantee that
+    /// shells and the standard library's fd-duplication tricks (e.g.
+    /// redirecting stdout by closing fd 1 then opening) depend on. Reuses a
+    /// slot freed by `sys_close` instead of always growing the table. Wraps
+    /// `file` in a fresh `OpenFileDescription` of its own — a later
+    /// `dup`/`dup2` of the returned fd is what shares it, not a second call
+    /// to `alloc_fd` with the same `file`.
+    pub fn alloc_fd(&mut self, file: Arc<dyn File>) -> usize {
+        self.install_fd(OpenFileDescription::new(file), FdFlags::empty())
+    }
+
+    /// Install an already-constructed `(description, flags)` pair at the
+    /// lowest unused fd, same slot-reuse policy as `alloc_fd`. The shared
+    /// primitive behind `alloc_fd` (fresh description) and `dup_fd`
+    /// (cloned description, fresh flags).
+    fn install_fd(&mut self, description: Arc<OpenFileDescription>, flags: FdFlags) -> usize {
+        match self.fd_table.iter().position(|f| f.is_none()) {
+            Some(fd) => {
+                self.fd_table[fd] = Some((description, flags));
+                fd
+            }
+            None => {
+                self.fd_table.push(Some((description, flags)));
+                self.fd_table.len() - 1
+            }
+        }
+    }
+
+    /// The open file description installed at `fd`, if any — the shared
+    /// state every generic fd-based syscall (`read`, `write`, `poll`, ...)
+    /// actually needs, without exposing `fd_table`'s `(Arc<OFD>, FdFlags)`
+    /// pairing to every call site.
+    pub fn fd_description(&self, fd: usize) -> Option<Arc<OpenFileDescription>> {
+        self.fd_table.get(fd)?.as_ref().map(|(d, _)| d.clone())
+    }
+
+    /// The file behind `fd`, if any — `fd_description(fd).file` for call
+    /// sites that never need the description itself.
+    pub fn fd_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_description(fd).map(|d| d.file.clone())
+    }
+
+    /// `dup(2)`: install a new fd at the lowest unused number, sharing
+    /// `old_fd`'s open file description (so the two fds see the same cursor
+    /// and status) but starting with fresh (cleared) `FdFlags` — `dup`'d
+    /// fds never inherit `FD_CLOEXEC`, same as the real syscall. Returns
+    /// `None` if `old_fd` isn't open.
+    pub fn dup_fd(&mut self, old_fd: usize) -> Option<usize> {
+        let description = self.fd_description(old_fd)?;
+        Some(self.install_fd(description, FdFlags::empty()))
+    }
+
+    /// `dup2(2)`: like `dup_fd`, but at the caller-chosen `new_fd` rather
+    /// than the lowest unused one, closing whatever `new_fd` previously held
+    /// first. A no-op returning `new_fd` when `old_fd == new_fd` and it's
+    /// open, matching the real syscall rather than closing and reopening the
+    /// same description. Returns `None` if `old_fd` isn't open.
+    pub fn dup2_fd(&mut self, old_fd: usize, new_fd: usize) -> Option<usize> {
+        let description = self.fd_description(old_fd)?;
+        if old_fd == new_fd {
+            return Some(new_fd);
+        }
+        if new_fd >= self.fd_table.len() {
+            self.fd_table.resize_with(new_fd + 1, || None);
+        }
+        self.fd_table[new_fd] = Some((description, FdFlags::empty()));
+        Some(new_fd)
+    }
+}
+
+impl ProcessControlBlock {
+    pub fn new(memory_set: MemorySet) -> Arc<Self> {
+        let pid = pid_alloc();
+        // A fresh process starts as its own group leader, same as a real
+        // `fork`+`exec` session leader before anything calls `setpgid` —
+        // there's no "inherit the parent's pgid" path to wire up since this
+        // tree has no `fork`/`clone` syscall that would actually produce a
+        // child to inherit one.
+        let pgid = pid.0;
+        let process = Arc::new(Self {
+            pid,
+            inner: unsafe {
+                UPSafeCell::new(ProcessControlBlockInner {
+                    memory_set,
+                    parent: None,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    tasks: Vec::new(),
+                    fd_table: initial_fd_table(),
+                    uid: 0,
+                    gid: 0,
+                    signal_actions: alloc::vec![SignalAction::default(); MAX_SIG as usize + 1],
+                    rlimit_stack: crate::config::USER_STACK_SIZE,
+                    cwd: String::from("/"),
+                    root: String::from("/"),
+                    file_mappings: BTreeMap::new(),
+                    umask: 0o022,
+                    pgid,
+                    sid: pgid,
+                })
+            },
+        });
+        PID2PROCESS
+            .exclusive_access()
+            .insert(process.pid(), Arc::downgrade(&process));
+        process
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid.0
+    }
+
+    pub fn inner_exclusive_access(&self) -> core::cell::RefMut<'_, ProcessControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+}
+
+lazy_static! {
+    /// Every live process, by pid, for lookups that cross process
+    /// boundaries (`sys_kill` needs to find a process it doesn't already
+    /// hold an `Arc` to). Entries are never removed; dead processes are
+    /// simply weak references that no longer upgrade.
+    static ref PID2PROCESS: UPSafeCell<BTreeMap<usize, Weak<ProcessControlBlock>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Look up a still-live process by pid.
+pub fn process_by_pid(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PROCESS.exclusive_access().get(&pid)?.upgrade()
+}
+
+/// Every process still live right now, upgraded from `PID2PROCESS`'s weak
+/// references — a dead entry (one whose last `Arc` was dropped) simply
+/// fails to upgrade and is skipped rather than removed, same as
+/// `process_by_pid`. This is the iterator a system-wide memory audit (e.g.
+/// `sys_meminfo`) walks to visit every live process's `MemorySet`, since
+/// `MemorySet` itself isn't separately registered anywhere — a process's
+/// address space never outlives the process that owns it.
+pub fn all_live_processes() -> Vec<Arc<ProcessControlBlock>> {
+    PID2PROCESS
+        .exclusive_access()
+        .values()
+        .filter_map(Weak::upgrade)
+        .collect()
+}
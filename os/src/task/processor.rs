@@ -0,0 +1,46 @@
+//! The single-hart "current task" slot. A real multi-hart build would have
+//! one `Processor` per hart; this kernel targets a single hart.
+
+use super::task::TaskControlBlock;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+pub struct Processor {
+    current: Option<Arc<TaskControlBlock>>,
+}
+
+impl Processor {
+    pub fn new() -> Self {
+        Self { current: None }
+    }
+    pub fn take_current(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.current.take()
+    }
+    pub fn current(&self) -> Option<Arc<TaskControlBlock>> {
+        self.current.clone()
+    }
+    pub fn set_current(&mut self, task: Option<Arc<TaskControlBlock>>) {
+        self.current = task;
+    }
+}
+
+lazy_static! {
+    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+}
+
+pub fn current_task() -> Option<Arc<TaskControlBlock>> {
+    PROCESSOR.exclusive_access().current()
+}
+
+pub fn current_user_token() -> usize {
+    let task = current_task().unwrap();
+    let token = task.process().inner_exclusive_access().memory_set.token();
+    token
+}
+
+/// Give up the rest of the current task's timeslice. This tree has no
+/// ready-queue scheduler wired up yet, so there's no other task to switch
+/// to; callers that need to wait for a condition (timers, blocking I/O)
+/// call this in a loop and re-check the condition each time.
+pub fn suspend_current_and_run_next() {}
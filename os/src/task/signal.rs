@@ -0,0 +1,66 @@
+//! Minimal POSIX-style signal state: enough pending/mask/handler-table
+//! plumbing for `sys_kill`/`sys_sigaction` (this commit) and
+//! `sys_sigprocmask` (next) to build on. There's no user-space dispatch
+//! trampoline yet — a registered handler is recorded but never actually
+//! invoked; delivery today only means "mark pending" for things like
+//! `sys_pause` to observe.
+
+use bitflags::bitflags;
+
+bitflags! {
+    pub struct SignalFlags: u32 {
+        const SIGHUP    = 1 << 1;
+        const SIGINT    = 1 << 2;
+        const SIGQUIT   = 1 << 3;
+        const SIGILL    = 1 << 4;
+        const SIGTRAP   = 1 << 5;
+        const SIGABRT   = 1 << 6;
+        const SIGBUS    = 1 << 7;
+        const SIGFPE    = 1 << 8;
+        const SIGKILL   = 1 << 9;
+        const SIGUSR1   = 1 << 10;
+        const SIGSEGV   = 1 << 11;
+        const SIGUSR2   = 1 << 12;
+        const SIGPIPE   = 1 << 13;
+        const SIGALRM   = 1 << 14;
+        const SIGTERM   = 1 << 15;
+        const SIGCHLD   = 1 << 17;
+        const SIGCONT   = 1 << 18;
+        const SIGSTOP   = 1 << 19;
+    }
+}
+
+/// Highest signal number `from_signo` range-checks against. The highest
+/// bit actually defined in [`SignalFlags`] is `SIGSTOP` (19); a number in
+/// range with no defined bit (e.g. 16, or 20..=31) still maps to `None`.
+pub const MAX_SIG: i32 = 31;
+
+impl SignalFlags {
+    /// The single-signal flag for POSIX signal number `signo`, or `None`
+    /// if it's out of range or unrecognized.
+    pub fn from_signo(signo: i32) -> Option<Self> {
+        if signo <= 0 || signo > MAX_SIG {
+            return None;
+        }
+        Self::from_bits(1 << signo)
+    }
+}
+
+/// One `sigaction(2)` entry: the handler address (interpreted as `SIG_DFL`/
+/// `SIG_IGN`/a user-space function pointer the same way `libc` does) plus
+/// the additional signals to block while it runs.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! The thread-level task control block. A process (`ProcessControlBlock`)
+//! owns one or more `TaskControlBlock`s that share its address space.
+
+use super::context::TaskContext;
+use super::process::ProcessControlBlock;
+use super::signal::SignalFlags;
+use crate::mm::PhysPageNum;
+use crate::sync::UPSafeCell;
+use alloc::sync::{Arc, Weak};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TaskStatus {
+    Ready,
+    Running,
+    Blocked,
+    Zombie,
+}
+
+pub struct TaskControlBlock {
+    pub process: Weak<ProcessControlBlock>,
+    pub kstack: usize,
+    inner: UPSafeCell<TaskControlBlockInner>,
+}
+
+pub struct TaskControlBlockInner {
+    pub trap_cx_ppn: PhysPageNum,
+    pub task_cx: TaskContext,
+    pub task_status: TaskStatus,
+    pub exit_code: Option<i32>,
+    /// Signals delivered to this task that haven't been observed yet, e.g.
+    /// by `sys_pause`. Set by `sys_kill`. A signal can be pending and
+    /// blocked at the same time — it stays in this set until unblocked.
+    pub pending_signals: SignalFlags,
+    /// Signals currently blocked via `sys_sigprocmask`. Blocked signals
+    /// still get OR-ed into `pending_signals` by `sys_kill`; they just
+    /// don't count as "delivered" for things like `sys_pause` until
+    /// unblocked.
+    pub signal_mask: SignalFlags,
+}
+
+impl TaskControlBlock {
+    pub fn new(
+        process: Weak<ProcessControlBlock>,
+        kstack: usize,
+        trap_cx_ppn: PhysPageNum,
+    ) -> Self {
+        Self {
+            process,
+            kstack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    task_cx: TaskContext::goto_trap_return(kstack),
+                    task_status: TaskStatus::Ready,
+                    exit_code: None,
+                    pending_signals: SignalFlags::empty(),
+                    signal_mask: SignalFlags::empty(),
+                })
+            },
+        }
+    }
+
+    pub fn inner_exclusive_access(&self) -> core::cell::RefMut<'_, TaskControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+
+    pub fn process(&self) -> Arc<ProcessControlBlock> {
+        self.process.upgrade().unwrap()
+    }
+}
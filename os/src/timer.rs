@@ -0,0 +1,38 @@
+//! Timekeeping: reads the hart's `time` CSR as a monotonic tick counter.
+
+use crate::config::CLOCK_FREQ;
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: u64,
+    pub nsec: u64,
+}
+
+#[cfg(target_arch = "riscv64")]
+fn read_time() -> u64 {
+    riscv::register::time::read64()
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn read_time() -> u64 {
+    0
+}
+
+/// Ticks since boot from a source that never goes backwards, unlike a
+/// wall-clock time that can be stepped by `sys_settimeofday` (not
+/// implemented here, but the CLOCK_MONOTONIC contract still matters).
+pub fn get_time() -> u64 {
+    read_time()
+}
+
+pub fn get_time_spec() -> TimeSpec {
+    let ticks = get_time();
+    let ns = ticks as u128 * NSEC_PER_SEC as u128 / CLOCK_FREQ as u128;
+    TimeSpec {
+        sec: (ns / NSEC_PER_SEC as u128) as u64,
+        nsec: (ns % NSEC_PER_SEC as u128) as u64,
+    }
+}